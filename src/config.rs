@@ -0,0 +1,374 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// ~/.config/fdbdir/config.toml, e.g.
+//
+//   [profiles.prod]
+//   cluster_file = "/etc/foundationdb/prod.cluster"
+//   tls_cert_file = "/etc/foundationdb/cert.pem"
+//   tls_key_file = "/etc/foundationdb/key.pem"
+//   tls_ca_file = "/etc/foundationdb/ca.pem"
+//   api_version = 710
+//
+// Only the TOML this file needs is understood: [table] headers and
+// `key = value` lines with string, integer, or boolean values.
+pub fn config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".config").join("fdbdir").join("config.toml"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+// Table name ("" for top-level keys) to its keys.
+pub type Tables = BTreeMap<String, BTreeMap<String, Value>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub cluster_file: Option<String>,
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    pub tls_ca_file: Option<String>,
+    pub api_version: Option<i32>,
+}
+
+// A missing file reads as empty.
+pub fn load() -> Result<Tables> {
+    let Some(path) = config_path() else {
+        return Ok(Tables::new());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).with_context(|| format!("in {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Tables::new()),
+        Err(e) => Err(e).with_context(|| format!("cannot read {}", path.display())),
+    }
+}
+
+pub fn profile(tables: &Tables, name: &str) -> Result<Profile> {
+    let Some(table) = tables.get(&format!("profiles.{name}")) else {
+        let known: Vec<&str> = tables
+            .keys()
+            .filter_map(|t| t.strip_prefix("profiles."))
+            .collect();
+        return Err(if known.is_empty() {
+            anyhow!("no profile '{name}': no [profiles.<name>] tables in the config file")
+        } else {
+            anyhow!("no profile '{name}' (have: {})", known.join(", "))
+        });
+    };
+    let mut profile = Profile::default();
+    for (key, value) in table {
+        let slot = match key.as_str() {
+            "cluster_file" => &mut profile.cluster_file,
+            "tls_cert_file" => &mut profile.tls_cert_file,
+            "tls_key_file" => &mut profile.tls_key_file,
+            "tls_ca_file" => &mut profile.tls_ca_file,
+            "api_version" => {
+                let Value::Int(v) = value else {
+                    return Err(anyhow!("profile '{name}': api_version must be an integer"));
+                };
+                profile.api_version = Some(i32::try_from(*v)?);
+                continue;
+            }
+            _ => return Err(anyhow!("profile '{name}': unknown key '{key}'")),
+        };
+        let Value::Str(s) = value else {
+            return Err(anyhow!("profile '{name}': {key} must be a string"));
+        };
+        *slot = Some(s.clone());
+    }
+    Ok(profile)
+}
+
+pub fn parse(text: &str) -> Result<Tables> {
+    let mut tables = Tables::new();
+    let mut current = String::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let lineno = idx + 1;
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| anyhow!("line {lineno}: expected ']'"))?;
+            current = name.trim().to_string();
+            tables.entry(current.clone()).or_default();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {lineno}: expected key = value"))?;
+        let value = parse_value(value.trim()).with_context(|| format!("line {lineno}"))?;
+        tables
+            .entry(current.clone())
+            .or_default()
+            .insert(key.trim().to_string(), value);
+    }
+    Ok(tables)
+}
+
+// Drops a trailing `# comment`, leaving '#' inside quoted strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_str => escaped = true,
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(s: &str) -> Result<Value> {
+    if let Some(body) = s.strip_prefix('"') {
+        let body = body
+            .strip_suffix('"')
+            .ok_or_else(|| anyhow!("unterminated string"))?;
+        let mut out = String::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                other => return Err(anyhow!("unsupported escape \\{}", other.unwrap_or(' '))),
+            }
+        }
+        return Ok(Value::Str(out));
+    }
+    match s {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ => s
+            .replace('_', "")
+            .parse()
+            .map(Value::Int)
+            .map_err(|_| anyhow!("expected a quoted string, integer, or true/false")),
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Str(s) => {
+            let escaped = s
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\t', "\\t");
+            format!("\"{escaped}\"")
+        }
+        Value::Int(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+    }
+}
+
+// Sets `key` in `[table]`, editing the file in place so comments and other
+// tables survive; the file and its directory are created if missing.
+pub fn store(table: &str, key: &str, value: &Value) -> Result<()> {
+    let path = config_path().ok_or_else(|| anyhow!("no home directory for the config file"))?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("cannot read {}", path.display())),
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+    }
+    std::fs::write(&path, update(&text, table, key, value))
+        .with_context(|| format!("cannot write {}", path.display()))
+}
+
+// `text` with `key` set in `[table]`. An existing entry is replaced where it
+// stands (keeping its trailing comment), a new one goes after the table's
+// last entry, and a new table is appended at the end.
+fn update(text: &str, table: &str, key: &str, value: &Value) -> String {
+    let entry = format!("{key} = {}", format_value(value));
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let table_name = |l: &str| {
+        let l = strip_comment(l).trim();
+        l.strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+            .map(|name| name.trim().to_string())
+    };
+    let start = lines
+        .iter()
+        .position(|l| table_name(l).is_some_and(|name| name == table));
+    match start {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| table_name(l).is_some())
+                .map_or(lines.len(), |i| start + 1 + i);
+            let existing = (start + 1..end).find(|&i| {
+                strip_comment(&lines[i])
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim() == key)
+            });
+            match existing {
+                Some(i) => {
+                    let comment = &lines[i][strip_comment(&lines[i]).len()..];
+                    lines[i] = match comment {
+                        "" => entry,
+                        comment => format!("{entry} {comment}"),
+                    };
+                }
+                None => {
+                    // After the table's last non-blank line.
+                    let at = (start + 1..end)
+                        .rev()
+                        .find(|&i| !lines[i].trim().is_empty())
+                        .map_or(start + 1, |i| i + 1);
+                    lines.insert(at, entry);
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{table}]"));
+            lines.push(entry);
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"# fdbdir settings
+top = 1
+
+[profiles.prod]   # the big one
+cluster_file = "/etc/fdb # not a comment.cluster"  # but this is
+api_version = 710
+
+[ settings ]
+limit = 50 # rows
+color = false
+quoted = "say \"hi\"\tand\\ back\nslash"
+"#;
+
+    fn table<'a>(tables: &'a Tables, name: &str) -> Vec<(&'a str, &'a Value)> {
+        tables[name].iter().map(|(k, v)| (k.as_str(), v)).collect()
+    }
+
+    #[test]
+    fn parses_tables_comments_and_strings() {
+        let tables = parse(SAMPLE).unwrap();
+        assert_eq!(
+            tables.keys().collect::<Vec<_>>(),
+            ["", "profiles.prod", "settings"]
+        );
+        assert_eq!(table(&tables, ""), [("top", &Value::Int(1))]);
+        assert_eq!(
+            table(&tables, "profiles.prod"),
+            [
+                ("api_version", &Value::Int(710)),
+                (
+                    "cluster_file",
+                    &Value::Str("/etc/fdb # not a comment.cluster".into())
+                ),
+            ]
+        );
+        assert_eq!(
+            table(&tables, "settings"),
+            [
+                ("color", &Value::Bool(false)),
+                ("limit", &Value::Int(50)),
+                (
+                    "quoted",
+                    &Value::Str("say \"hi\"\tand\\ back\nslash".into())
+                ),
+            ]
+        );
+        let profile = profile(&tables, "prod").unwrap();
+        assert_eq!(profile.api_version, Some(710));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for bad in [
+            "[settings",
+            "limit",
+            "limit = ",
+            "limit = fifty",
+            "name = \"open",
+            r#"name = "bad \q escape""#,
+        ] {
+            assert!(parse(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn update_replaces_a_key_in_place() {
+        let text = update(SAMPLE, "settings", "limit", &Value::Int(7));
+        let expected = SAMPLE.replace("limit = 50 # rows", "limit = 7 # rows");
+        assert_eq!(text, expected);
+        // A key of the same name in another table is left alone.
+        let text = update(SAMPLE, "profiles.prod", "api_version", &Value::Int(730));
+        assert_eq!(
+            text,
+            SAMPLE.replace("api_version = 710", "api_version = 730")
+        );
+    }
+
+    #[test]
+    fn update_inserts_keys_and_tables() {
+        let text = update(
+            SAMPLE,
+            "profiles.prod",
+            "tls_ca_file",
+            &Value::Str("ca.pem".into()),
+        );
+        let expected = SAMPLE.replace(
+            "api_version = 710\n",
+            "api_version = 710\ntls_ca_file = \"ca.pem\"\n",
+        );
+        assert_eq!(text, expected);
+
+        let text = update(SAMPLE, "profiles.dev", "api_version", &Value::Int(710));
+        assert_eq!(
+            text,
+            format!("{SAMPLE}\n[profiles.dev]\napi_version = 710\n")
+        );
+
+        assert_eq!(
+            update("", "settings", "pager", &Value::Bool(true)),
+            "[settings]\npager = true\n"
+        );
+    }
+
+    #[test]
+    fn stored_values_parse_back() {
+        let values = [
+            Value::Str("tab\tnew\nline \"quote\" back\\slash # hash".into()),
+            Value::Str(String::new()),
+            Value::Int(-3),
+            Value::Bool(true),
+        ];
+        for value in values {
+            let text = update(SAMPLE, "settings", "streaming", &value);
+            let tables = parse(&text).unwrap();
+            assert_eq!(tables["settings"]["streaming"], value, "{text}");
+        }
+    }
+}
@@ -0,0 +1,886 @@
+use crate::format::{
+    display_path, format_element, format_key, format_value, paint_value, FormatOptions,
+};
+use crate::util::{content_prefix, describe_error, dir_exists, list_dirs, Client, ProgressBar};
+use anyhow::{anyhow, Context, Result};
+use foundationdb::directory::Directory;
+use foundationdb::tuple::{Element, TupleUnpack};
+use foundationdb::RangeOption;
+use futures_util::{StreamExt, TryStreamExt};
+use owo_colors::OwoColorize;
+use std::io::{BufRead, Write};
+
+// Dump format (one record per line, keys relative to the directory prefix):
+//
+//   # fdbdir dump v1
+//   # path: /app/foo
+//   <hex key>\t<hex value>
+//
+// Blank lines and lines starting with '#' are ignored on import.
+const HEADER: &str = "# fdbdir dump v1";
+
+// Keys read per transaction during export/copy; keeps each read well inside the 5s limit.
+const EXPORT_BATCH_KEYS: usize = 10_000;
+
+// Bytes written per transaction during import/copy; FDB rejects transactions over 10MB.
+const IMPORT_BATCH_BYTES: usize = 1_000_000;
+
+// Target shard size when splitting a parallel export.
+const SHARD_BYTES: i64 = 16_000_000;
+
+// Keys groupby reads unless given --limit or --all.
+pub const GROUPBY_LIMIT: usize = 10_000;
+
+// Width of the longest histogram bar.
+const BAR_WIDTH: usize = 40;
+
+// With `parallel` > 1 the range is split at the cluster's split points and up to
+// `parallel` shards are read at once; output stays in key order.
+pub async fn export_path<W: Write>(
+    db: &Client,
+    path: Vec<String>,
+    out: &mut W,
+    parallel: usize,
+    max_scan_bytes: Option<u64>,
+    progress_bar: bool,
+) -> Result<usize> {
+    let (prefix, end, estimate) = crate::util::run(db, |trx, _| {
+        let path = path.clone();
+        async move {
+            let dir = db.dir_for_path(&trx, &path, None).await?;
+            let prefix = content_prefix(&dir)?.to_vec();
+            let (begin, end) = dir.range()?;
+            if let Some(max) = max_scan_bytes {
+                crate::util::check_scan_size(&trx, &begin, &end, max).await?;
+            }
+            let estimate = if progress_bar {
+                Some(trx.get_estimated_range_size_bytes(&begin, &end).await?)
+            } else {
+                None
+            };
+            Ok((prefix, end, estimate))
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    let mut bar = estimate.map(ProgressBar::start);
+
+    writeln!(out, "{HEADER}")?;
+    writeln!(out, "# path: {}", display_path(&path))?;
+
+    let shards = if parallel > 1 {
+        split_range(db, &prefix, &end).await?
+    } else {
+        Vec::new()
+    };
+    // Small ranges come back as a single shard; a plain serial scan is cheaper.
+    if shards.len() > 1 {
+        let mut total = 0usize;
+        let mut results = futures_util::stream::iter(shards)
+            .map(|(begin, end)| async move { read_shard(db, &begin, &end).await })
+            .buffered(parallel);
+        while let Some(rows) = results.try_next().await? {
+            for (key, val) in rows.iter() {
+                writeln!(
+                    out,
+                    "{}\t{}",
+                    hex::encode(&key[prefix.len()..]),
+                    hex::encode(val)
+                )?;
+                if let Some(bar) = bar.as_mut() {
+                    bar.add(key.len() + val.len());
+                }
+            }
+            total += rows.len();
+        }
+        out.flush()?;
+        return Ok(total);
+    }
+
+    // Read in batches across transactions, resuming just past the last key.
+    let mut begin = prefix.clone();
+    let mut total = 0usize;
+    loop {
+        let batch = read_batch(db, &begin, &end).await?;
+        for (key, val) in batch.iter() {
+            writeln!(
+                out,
+                "{}\t{}",
+                hex::encode(&key[prefix.len()..]),
+                hex::encode(val)
+            )?;
+            if let Some(bar) = bar.as_mut() {
+                bar.add(key.len() + val.len());
+            }
+        }
+        total += batch.len();
+        match next_begin(&batch) {
+            Some(next) => begin = next,
+            None => break,
+        }
+    }
+    out.flush()?;
+    Ok(total)
+}
+
+pub async fn copy_path<W: Write>(
+    db: &Client,
+    out: &mut W,
+    src: Vec<String>,
+    dst: Vec<String>,
+    layer: Option<Vec<u8>>,
+    dry_run: bool,
+) -> Result<usize> {
+    if dst.starts_with(&src) {
+        return Err(anyhow!("cannot copy {} into itself", display_path(&src)));
+    }
+    let (src_prefix, end, src_layer) = crate::util::run(db, |trx, _| {
+        let src = src.clone();
+        async move {
+            let dir = db.dir_for_path(&trx, &src, None).await?;
+            let prefix = content_prefix(&dir)?.to_vec();
+            let (_, end) = dir.range()?;
+            Ok((prefix, end, dir.get_layer().to_vec()))
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    // Preserve the source layer unless one was given explicitly.
+    let layer = layer.unwrap_or(src_layer);
+    // A dry run only counts the source keys, so the destination need not exist.
+    let dst_prefix = if dry_run {
+        if !dir_exists(db, &dst).await? {
+            writeln!(out, "would create {}", display_path(&dst).yellow())?;
+        }
+        Vec::new()
+    } else {
+        crate::util::run(db, |trx, _| {
+            let dst = dst.clone();
+            let layer = layer.clone();
+            async move {
+                let dl = db.directory_layer();
+                let layer = (!layer.is_empty()).then_some(layer.as_slice());
+                let dir = dl.create_or_open(&trx, &dst, None, layer).await?;
+                Ok(content_prefix(&dir)?.to_vec())
+            }
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?
+    };
+
+    writeln!(
+        out,
+        "-- {}copying {} to {} --",
+        if dry_run { "dry run: " } else { "" },
+        display_path(&src).yellow(),
+        display_path(&dst).yellow()
+    )?;
+    let mut begin = src_prefix.clone();
+    let mut total = 0usize;
+    loop {
+        let batch = read_batch(db, &begin, &end).await?;
+        let mut chunk: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        let mut chunk_bytes = 0usize;
+        for (key, val) in batch.iter() {
+            let mut new_key = dst_prefix.clone();
+            new_key.extend_from_slice(&key[src_prefix.len()..]);
+            chunk_bytes += new_key.len() + val.len();
+            chunk.push((new_key, val.clone()));
+            if chunk_bytes >= IMPORT_BATCH_BYTES {
+                write_batch(db, &chunk, ImportMode::Overwrite, dry_run).await?;
+                chunk.clear();
+                chunk_bytes = 0;
+            }
+        }
+        if !chunk.is_empty() {
+            write_batch(db, &chunk, ImportMode::Overwrite, dry_run).await?;
+        }
+        total += batch.len();
+        match next_begin(&batch) {
+            Some(next) => {
+                begin = next;
+                if !dry_run {
+                    writeln!(out, "{}", format!("… {total} copied").dimmed())?;
+                }
+            }
+            None => break,
+        }
+    }
+    if dry_run {
+        writeln!(
+            out,
+            "would copy {total} keys, overwriting any that exist; nothing written (dry run)"
+        )?;
+    } else {
+        writeln!(out, "copied {total} keys")?;
+    }
+    Ok(total)
+}
+
+async fn read_batch(db: &Client, begin: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    crate::util::run(db, |trx, _| async move {
+        let mut opt: RangeOption = (begin.to_vec(), end.to_vec()).into();
+        opt.limit = Some(EXPORT_BATCH_KEYS);
+        let mut rows = Vec::new();
+        let mut stream = trx.get_ranges_keyvalues(opt, true);
+        while let Some(item) = stream.try_next().await? {
+            rows.push((item.key().to_vec(), item.value().to_vec()));
+        }
+        Ok(rows)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Consecutive [begin, end) shards of roughly SHARD_BYTES covering the range.
+async fn split_range(db: &Client, begin: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let points = crate::util::run(db, |trx, _| async move {
+        let keys = trx.get_range_split_points(begin, end, SHARD_BYTES).await?;
+        Ok(keys.iter().map(|k| k.key().to_vec()).collect::<Vec<_>>())
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    Ok(points
+        .windows(2)
+        .map(|w| (w[0].clone(), w[1].clone()))
+        .collect())
+}
+
+// Every row of one shard, in batches across transactions.
+async fn read_shard(db: &Client, begin: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut rows = Vec::new();
+    let mut begin = begin.to_vec();
+    loop {
+        let batch = read_batch(db, &begin, end).await?;
+        let next = next_begin(&batch);
+        rows.extend(batch);
+        match next {
+            Some(next) => begin = next,
+            None => return Ok(rows),
+        }
+    }
+}
+
+// A full batch means there may be more: resume just past its last key.
+fn next_begin(batch: &[(Vec<u8>, Vec<u8>)]) -> Option<Vec<u8>> {
+    match batch.last() {
+        Some((key, _)) if batch.len() == EXPORT_BATCH_KEYS => {
+            let mut next = key.clone();
+            next.push(0x00);
+            Some(next)
+        }
+        _ => None,
+    }
+}
+
+// One side of a diff: the rows of a directory read a batch at a time.
+struct DiffSide {
+    dir: foundationdb::directory::DirectoryOutput,
+    prefix_len: usize,
+    // None once the last batch has been read.
+    begin: Option<Vec<u8>>,
+    end: Vec<u8>,
+    rows: std::collections::VecDeque<(Vec<u8>, Vec<u8>)>,
+}
+
+impl DiffSide {
+    async fn open(db: &Client, path: &[String]) -> Result<Self> {
+        let (dir, begin, end) = crate::util::run(db, |trx, _| async move {
+            let dir = db.dir_for_path(&trx, path, None).await?;
+            let begin = content_prefix(&dir)?.to_vec();
+            let (_, end) = dir.range()?;
+            Ok((dir, begin, end))
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+        Ok(Self {
+            dir,
+            prefix_len: begin.len(),
+            begin: Some(begin),
+            end,
+            rows: Default::default(),
+        })
+    }
+
+    // The next row's key relative to the directory, reading another batch
+    // when the current one is used up.
+    async fn peek(&mut self, db: &Client) -> Result<Option<&[u8]>> {
+        if self.rows.is_empty() {
+            if let Some(begin) = self.begin.take() {
+                let batch = read_batch(db, &begin, &self.end).await?;
+                self.begin = next_begin(&batch);
+                self.rows = batch.into();
+            }
+        }
+        Ok(self.rows.front().map(|(k, _)| &k[self.prefix_len..]))
+    }
+}
+
+// Rows only in `a`, only in `b`, in both with different values, and identical.
+#[derive(Default)]
+pub struct DiffCounts {
+    pub removed: usize,
+    pub added: usize,
+    pub changed: usize,
+    pub same: usize,
+}
+
+// Compares two directories' keys relative to their prefixes, merging both
+// ranges in key order a batch at a time so memory stays bounded. `verbose`
+// prints each differing key: `-` only in a, `+` only in b, `~` different values.
+pub async fn diff_paths<W: Write>(
+    db: &Client,
+    out: &mut W,
+    a: Vec<String>,
+    b: Vec<String>,
+    verbose: bool,
+    fmt: FormatOptions,
+) -> Result<DiffCounts> {
+    let mut left = DiffSide::open(db, &a).await?;
+    let mut right = DiffSide::open(db, &b).await?;
+    let mut counts = DiffCounts::default();
+    loop {
+        let l = left.peek(db).await?.map(<[u8]>::to_vec);
+        let r = right.peek(db).await?.map(<[u8]>::to_vec);
+        let order = match (&l, &r) {
+            (None, None) => break,
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (Some(l), Some(r)) => l.cmp(r),
+        };
+        match order {
+            std::cmp::Ordering::Less => {
+                let (key, val) = left.rows.pop_front().unwrap_or_default();
+                counts.removed += 1;
+                if verbose {
+                    writeln!(
+                        out,
+                        "{} {} {} {}",
+                        "-".red(),
+                        format_key(&left.dir, &key, &fmt).cyan(),
+                        "=>".dimmed(),
+                        paint_value(&val, &fmt)
+                    )?;
+                }
+            }
+            std::cmp::Ordering::Greater => {
+                let (key, val) = right.rows.pop_front().unwrap_or_default();
+                counts.added += 1;
+                if verbose {
+                    writeln!(
+                        out,
+                        "{} {} {} {}",
+                        "+".green(),
+                        format_key(&right.dir, &key, &fmt).cyan(),
+                        "=>".dimmed(),
+                        paint_value(&val, &fmt)
+                    )?;
+                }
+            }
+            std::cmp::Ordering::Equal => {
+                let (key, old) = left.rows.pop_front().unwrap_or_default();
+                let (_, new) = right.rows.pop_front().unwrap_or_default();
+                if old == new {
+                    counts.same += 1;
+                    continue;
+                }
+                counts.changed += 1;
+                if verbose {
+                    writeln!(
+                        out,
+                        "{} {} {} {} {} {}",
+                        "~".yellow(),
+                        format_key(&left.dir, &key, &fmt).cyan(),
+                        "=>".dimmed(),
+                        paint_value(&old, &fmt),
+                        "->".dimmed(),
+                        paint_value(&new, &fmt)
+                    )?;
+                }
+            }
+        }
+    }
+    writeln!(
+        out,
+        "{} only in {}, {} only in {}, {} differ, {} identical",
+        format!("-{}", counts.removed).red(),
+        display_path(&a).yellow(),
+        format!("+{}", counts.added).green(),
+        display_path(&b).yellow(),
+        format!("~{}", counts.changed).yellow(),
+        counts.same
+    )?;
+    Ok(counts)
+}
+
+// One directory of export-tree, before its children are nested into it.
+struct TreeNode {
+    doc: serde_json::Map<String, serde_json::Value>,
+    // None below --depth, where children are not read.
+    children: Option<Vec<usize>>,
+}
+
+// The directory hierarchy under `root` as one JSON document: each directory's
+// name, path, prefix and layer (hex; partitions have no prefix), and its
+// children, nested. Directories at `max_depth` below the root have no
+// "children" field. Returns the number of directories written.
+pub async fn export_tree<W: Write>(
+    db: &Client,
+    out: &mut W,
+    root: Vec<String>,
+    max_depth: Option<usize>,
+) -> Result<usize> {
+    // Depth-first, one transaction per directory like find.
+    let mut nodes: Vec<TreeNode> = Vec::new();
+    let mut stack = vec![(root, 0usize, None::<usize>)];
+    while let Some((path, depth, parent)) = stack.pop() {
+        let descend = max_depth.is_none_or(|max| depth < max);
+        let (prefix, layer, names) = crate::util::run(db, |trx, _| {
+            let path = path.clone();
+            async move {
+                let names = if descend {
+                    db.directory_layer().list(&trx, &path).await?
+                } else {
+                    Vec::new()
+                };
+                // The root is the directory layer itself, not a directory.
+                if path.is_empty() {
+                    return Ok((None, Vec::new(), names));
+                }
+                let dir = db.dir_for_path(&trx, &path, None).await?;
+                let prefix = dir.bytes().ok().map(|b| b.to_vec());
+                Ok((prefix, dir.get_layer().to_vec(), names))
+            }
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+        let mut doc = serde_json::Map::new();
+        doc.insert(
+            "name".into(),
+            path.last().cloned().unwrap_or_default().into(),
+        );
+        doc.insert("path".into(), display_path(&path).into());
+        doc.insert(
+            "partition".into(),
+            (!path.is_empty() && prefix.is_none()).into(),
+        );
+        doc.insert("prefix".into(), prefix.as_ref().map(hex::encode).into());
+        doc.insert("layer".into(), hex::encode(&layer).into());
+        let idx = nodes.len();
+        nodes.push(TreeNode {
+            doc,
+            children: descend.then(Vec::new),
+        });
+        if let Some(children) = parent.and_then(|p| nodes[p].children.as_mut()) {
+            children.push(idx);
+        }
+        // Push in reverse so children pop in list (lexicographic) order.
+        for name in names.into_iter().rev() {
+            let mut child = path.clone();
+            child.push(name);
+            stack.push((child, depth + 1, Some(idx)));
+        }
+    }
+    let count = nodes.len();
+    let tree = nest_tree(&mut nodes, 0);
+    writeln!(out, "{}", serde_json::to_string_pretty(&tree)?)?;
+    Ok(count)
+}
+
+fn nest_tree(nodes: &mut [TreeNode], idx: usize) -> serde_json::Value {
+    let mut doc = std::mem::take(&mut nodes[idx].doc);
+    if let Some(children) = nodes[idx].children.take() {
+        let children: Vec<serde_json::Value> =
+            children.into_iter().map(|c| nest_tree(nodes, c)).collect();
+        doc.insert("children".into(), children.into());
+    }
+    doc.into()
+}
+
+// Prints rows under `root` and its subdirectories whose decoded value matches
+// `re`, stopping after `limit` matches. Each directory is read in batches like
+// export, so large subtrees don't hit the 5s transaction limit.
+pub async fn grep_path<W: Write>(
+    db: &Client,
+    out: &mut W,
+    root: Vec<String>,
+    re: &regex::Regex,
+    limit: Option<usize>,
+    fmt: FormatOptions,
+) -> Result<usize> {
+    // Match against the whole value, not the --max-bytes display truncation.
+    let full = FormatOptions {
+        max_key_bytes: 0,
+        max_value_bytes: 0,
+        pretty: false,
+        ..fmt
+    };
+    let mut found = 0usize;
+    let mut stack = vec![root];
+    while let Some(path) = stack.pop() {
+        let (dir, range) = crate::util::run(db, |trx, _| {
+            let path = path.clone();
+            async move {
+                let dir = db.dir_for_path(&trx, &path, None).await?;
+                // Partitions hold no keys of their own; only descend into them.
+                let range = dir.bytes().is_ok().then(|| dir.range()).transpose()?;
+                Ok((dir, range))
+            }
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+        if let Some((mut begin, end)) = range {
+            loop {
+                let batch = read_batch(db, &begin, &end).await?;
+                for (key, val) in batch.iter() {
+                    if !re.is_match(&format_value(val, &full)) {
+                        continue;
+                    }
+                    if limit.is_some_and(|n| found >= n) {
+                        writeln!(
+                            out,
+                            "{}",
+                            "… more matches; raise --limit or use --all".dimmed()
+                        )?;
+                        return Ok(found);
+                    }
+                    found += 1;
+                    writeln!(
+                        out,
+                        "{} {} {} {}",
+                        display_path(&path).blue(),
+                        format_key(&dir, key, &fmt).cyan(),
+                        "=>".dimmed(),
+                        paint_value(val, &fmt)
+                    )?;
+                }
+                match next_begin(&batch) {
+                    Some(next) => begin = next,
+                    None => break,
+                }
+            }
+        }
+        // Push in reverse so children pop in list (lexicographic) order.
+        for name in list_dirs(db, &path).await?.into_iter().rev() {
+            let mut child = path.clone();
+            child.push(name);
+            stack.push(child);
+        }
+    }
+    Ok(found)
+}
+
+// Tallies the keys of a directory by the first element of their tuple and
+// prints the groups largest first, with a bar for each. Tuple keys sort by their
+// first element, so each group is one contiguous run of keys.
+pub async fn group_path<W: Write>(
+    db: &Client,
+    out: &mut W,
+    path: Vec<String>,
+    limit: Option<usize>,
+    fmt: FormatOptions,
+) -> Result<()> {
+    let (prefix, end) = crate::util::run(db, |trx, _| {
+        let path = path.clone();
+        async move {
+            let dir = db.dir_for_path(&trx, &path, None).await?;
+            let prefix = content_prefix(&dir)?.to_vec();
+            let (_, end) = dir.range()?;
+            Ok((prefix, end))
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+
+    let mut groups: Vec<(String, usize)> = Vec::new();
+    let mut untupled = 0usize;
+    let mut seen = 0usize;
+    let mut truncated = false;
+    let mut begin = prefix.clone();
+    'read: loop {
+        let batch = read_batch(db, &begin, &end).await?;
+        for (key, _) in batch.iter() {
+            if limit.is_some_and(|n| seen >= n) {
+                truncated = true;
+                break 'read;
+            }
+            seen += 1;
+            let label = match Element::unpack_root(&key[prefix.len()..]) {
+                Ok(Element::Tuple(items)) if !items.is_empty() => format_element(&items[0], &fmt),
+                _ => {
+                    untupled += 1;
+                    continue;
+                }
+            };
+            match groups.last_mut() {
+                Some((last, n)) if *last == label => *n += 1,
+                _ => groups.push((label, 1)),
+            }
+        }
+        match next_begin(&batch) {
+            Some(next) => begin = next,
+            None => break,
+        }
+    }
+    if untupled > 0 {
+        groups.push(("(not a tuple)".to_string(), untupled));
+    }
+    // Stable, so equal counts stay in key order.
+    groups.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+    writeln!(
+        out,
+        "-- {} keys in {} by first tuple element{} --",
+        seen,
+        display_path(&path).yellow(),
+        if truncated {
+            format!(" (stopped at limit {seen}; --all reads everything)")
+        } else {
+            String::new()
+        }
+    )?;
+    if groups.is_empty() {
+        writeln!(out, "(none)")?;
+        return Ok(());
+    }
+    let label_w = groups
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+    let count_w = groups[0].1.to_string().len();
+    for (label, n) in &groups {
+        let bar = (n * BAR_WIDTH).div_ceil(groups[0].1);
+        writeln!(
+            out,
+            "  {}{}  {n:>count_w$}  {}",
+            label.cyan(),
+            " ".repeat(label_w - label.chars().count()),
+            "█".repeat(bar).green()
+        )?;
+    }
+    writeln!(out, "{}", format!("{} groups", groups.len()).dimmed())?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    Overwrite,
+    SkipExisting,
+}
+
+pub async fn import_path<W: Write, R: BufRead>(
+    db: &Client,
+    out: &mut W,
+    path: Vec<String>,
+    input: R,
+    mode: ImportMode,
+    dry_run: bool,
+) -> Result<()> {
+    if path.is_empty() {
+        return Err(anyhow!("cannot import into the directory layer root"));
+    }
+    // A dry run leaves a missing directory uncreated; every key then counts as new.
+    let (prefix, mode) = if dry_run && !dir_exists(db, &path).await? {
+        writeln!(out, "would create {}", display_path(&path).yellow())?;
+        (Vec::new(), ImportMode::Overwrite)
+    } else {
+        let prefix = crate::util::run(db, |trx, _| {
+            let path = path.clone();
+            async move {
+                let dl = db.directory_layer();
+                let dir = dl.create_or_open(&trx, &path, None, None).await?;
+                Ok(content_prefix(&dir)?.to_vec())
+            }
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+        (prefix, mode)
+    };
+
+    writeln!(
+        out,
+        "-- {}importing into {} (prefix {}) --",
+        if dry_run { "dry run: " } else { "" },
+        display_path(&path).yellow(),
+        crate::format::format_bytes(&prefix, crate::format::DEFAULT_MAX_BYTES)
+    )?;
+
+    let mut batch: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    let mut batch_bytes = 0usize;
+    let mut written = 0usize;
+    let mut skipped = 0usize;
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (k, v) = parse_dump_line(line).with_context(|| format!("line {}", idx + 1))?;
+        let mut key = prefix.clone();
+        key.extend_from_slice(&k);
+        batch_bytes += key.len() + v.len();
+        batch.push((key, v));
+        if batch_bytes >= IMPORT_BATCH_BYTES {
+            let (w, s) = write_batch(db, &batch, mode, dry_run).await?;
+            written += w;
+            skipped += s;
+            writeln!(
+                out,
+                "{}",
+                format!("… {written} written, {skipped} skipped").dimmed()
+            )?;
+            batch.clear();
+            batch_bytes = 0;
+        }
+    }
+    if !batch.is_empty() {
+        let (w, s) = write_batch(db, &batch, mode, dry_run).await?;
+        written += w;
+        skipped += s;
+    }
+    if dry_run {
+        writeln!(
+            out,
+            "would import {written} keys ({skipped} skipped); nothing written (dry run)"
+        )?;
+    } else {
+        writeln!(out, "imported {written} keys ({skipped} skipped)")?;
+    }
+    Ok(())
+}
+
+async fn write_batch(
+    db: &Client,
+    batch: &[(Vec<u8>, Vec<u8>)],
+    mode: ImportMode,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    // A dry run still reads (to count skips) but sets nothing, so the commit is empty.
+    crate::util::run(db, |trx, _| async move {
+        let mut written = 0usize;
+        let mut skipped = 0usize;
+        for (key, val) in batch {
+            if mode == ImportMode::SkipExisting && trx.get(key, false).await?.is_some() {
+                skipped += 1;
+                continue;
+            }
+            if !dry_run {
+                trx.set(key, val);
+            }
+            written += 1;
+        }
+        Ok((written, skipped))
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+fn parse_dump_line(line: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (k, v) = line
+        .split_once('\t')
+        .ok_or_else(|| anyhow!("expected <hex key>\\t<hex value>"))?;
+    let key = hex::decode(k.trim()).map_err(|e| anyhow!("invalid key hex: {e}"))?;
+    let val = hex::decode(v.trim()).map_err(|e| anyhow!("invalid value hex: {e}"))?;
+    Ok((key, val))
+}
+
+// `load` file format, for hand-written fixtures: one `key<TAB>value` per line,
+// each side a byte literal (`user\x00`, `b64:...`) or a tuple literal
+// (`("users", 42)`), keys relative to the directory. Blank lines and lines
+// starting with '#' are skipped.
+fn parse_load_line(line: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (k, v) = line
+        .split_once('\t')
+        .ok_or_else(|| anyhow!("expected <key>\\t<value>"))?;
+    let key = crate::parse::parse_key_literal(k.trim()).context("invalid key")?;
+    let val = crate::parse::parse_key_literal(v.trim()).context("invalid value")?;
+    Ok((key, val))
+}
+
+// The whole file is parsed before anything is written, so a typo on line 90
+// doesn't leave the first 89 keys loaded (or the directory cleared).
+pub async fn load_path<W: Write, R: BufRead>(
+    db: &Client,
+    out: &mut W,
+    path: Vec<String>,
+    input: R,
+    clear_first: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if path.is_empty() {
+        return Err(anyhow!("cannot load into the directory layer root"));
+    }
+    let mut rows = Vec::new();
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        rows.push(parse_load_line(line).with_context(|| format!("line {}", idx + 1))?);
+    }
+    if dry_run {
+        if clear_first {
+            writeln!(out, "would clear {}", display_path(&path).yellow())?;
+        }
+        writeln!(
+            out,
+            "would load {} keys into {}; nothing written (dry run)",
+            rows.len(),
+            display_path(&path).yellow()
+        )?;
+        return Ok(());
+    }
+
+    // Created if missing; cleared in the same transaction, so the directory
+    // is never seen half-cleared.
+    let prefix = crate::util::run(db, |trx, _| {
+        let path = path.clone();
+        async move {
+            let dir = db
+                .directory_layer()
+                .create_or_open(&trx, &path, None, None)
+                .await?;
+            if clear_first {
+                let (begin, end) = dir.range()?;
+                trx.clear_range(&begin, &end);
+            }
+            Ok(content_prefix(&dir)?.to_vec())
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    if clear_first {
+        writeln!(out, "cleared {}", display_path(&path).yellow())?;
+    }
+
+    let mut batch: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    let mut batch_bytes = 0usize;
+    let mut written = 0usize;
+    for (k, v) in rows {
+        let mut key = prefix.clone();
+        key.extend_from_slice(&k);
+        batch_bytes += key.len() + v.len();
+        batch.push((key, v));
+        if batch_bytes >= IMPORT_BATCH_BYTES {
+            written += write_batch(db, &batch, ImportMode::Overwrite, false)
+                .await?
+                .0;
+            writeln!(out, "{}", format!("… {written} written").dimmed())?;
+            batch.clear();
+            batch_bytes = 0;
+        }
+    }
+    if !batch.is_empty() {
+        written += write_batch(db, &batch, ImportMode::Overwrite, false)
+            .await?
+            .0;
+    }
+    writeln!(
+        out,
+        "loaded {written} keys into {}",
+        display_path(&path).yellow()
+    )?;
+    Ok(())
+}
@@ -0,0 +1,1227 @@
+use crate::parse::{PathPattern, GLOB_CHARS};
+use crate::util::{
+    DirEntry, Listing, LsOptions, OutputFormat, ReadTiming, ScanFormat, ScanOptions, ScanRow,
+    ScanSink, Scanned, COUNT_CAP,
+};
+use anyhow::Result;
+use foundationdb::directory::DirectoryOutput;
+use foundationdb::tuple::{Element, TupleUnpack};
+use owo_colors::OwoColorize;
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FormatOptions {
+    pub pretty: bool,
+    pub hex_keys: bool,
+    pub hex_values: bool,
+    pub int_hex: bool,
+    // Show keys as escaped bytes without attempting tuple decoding.
+    pub raw_keys: bool,
+    // Render undecodable keys/values as `b64:...` instead of `\xHH` escapes.
+    pub b64: bool,
+    // Show versionstamps as their 12 raw bytes instead of version/batch/user fields.
+    pub vs_hex: bool,
+    // Fixed number of decimals for floats/doubles (None = shortest round-trip form).
+    pub precision: Option<usize>,
+    // Drop the f32/f64 suffix from floats/doubles.
+    pub bare_floats: bool,
+    // Byte strings and hex dumps in keys (and in key-like bounds and cursors)
+    // are truncated after this many bytes (0 = no limit).
+    pub max_key_bytes: usize,
+    // The same for values, which tend to be much larger than keys.
+    pub max_value_bytes: usize,
+    // Print every value green instead of coloring tuple elements by type.
+    pub flat_colors: bool,
+    // Show whole database keys (directory prefix included) as escaped bytes
+    // instead of decoding them relative to the directory.
+    pub absolute_keys: bool,
+    // Decode byte-string elements that are themselves packed tuples.
+    pub deep_decode: bool,
+    pub value_as: Option<ValueAs>,
+    // ls/scan rows are cut to this many columns with a trailing '…' (None =
+    // printed whole); set to the terminal's width unless output is piped or --full.
+    pub width: Option<usize>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            hex_keys: false,
+            hex_values: false,
+            int_hex: false,
+            raw_keys: false,
+            b64: false,
+            vs_hex: false,
+            precision: None,
+            bare_floats: false,
+            max_key_bytes: DEFAULT_MAX_BYTES,
+            max_value_bytes: DEFAULT_MAX_BYTES,
+            flat_colors: false,
+            absolute_keys: false,
+            deep_decode: false,
+            value_as: None,
+            width: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    // Continuation lines of pretty output line up under the text following
+    // the "NNNN. " row number.
+    fn indent(&self) -> Option<usize> {
+        self.pretty.then_some(6)
+    }
+}
+
+// How to read values that aren't tuples (--value-as); values that don't fit
+// the chosen type fall back to the usual tuple/text/bytes rendering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ValueAs {
+    // Big-endian two's complement, 1 to 8 bytes.
+    Int,
+    // Exactly 16 bytes.
+    Uuid,
+    // Text, even when the bytes would also decode as a tuple.
+    Utf8,
+    // Two hex digits per byte.
+    Hex,
+    // What atomic Add leaves behind: little-endian two's complement, 1 to 8 bytes.
+    LittleEndianInt,
+}
+
+// The inverse of parse_path: names it would read differently are quoted,
+// and so are names with glob characters, which `ls` would otherwise match.
+pub fn display_path(path: &[String]) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.iter().map(|name| display_name(name)).collect()
+}
+
+// A glob path as typed, patterns unquoted.
+pub fn display_path_pattern(path: &PathPattern) -> String {
+    if path.segments.is_empty() {
+        return "/".to_string();
+    }
+    path.segments
+        .iter()
+        .map(|(name, glob)| {
+            if *glob {
+                format!("/{name}")
+            } else {
+                display_name(name)
+            }
+        })
+        .collect()
+}
+
+fn display_name(name: &str) -> String {
+    let plain = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.trim() == name
+        && !name.contains(['/', '"'])
+        && !name.contains(GLOB_CHARS);
+    if plain {
+        format!("/{name}")
+    } else {
+        let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("/\"{escaped}\"")
+    }
+}
+
+pub fn format_element(el: &Element<'_>, fmt: &FormatOptions) -> String {
+    format_element_at(el, fmt, fmt.indent(), false)
+}
+
+// With `indent` set, a tuple that contains nested tuples is broken across
+// lines, one member per line, indented two columns per level past `indent`.
+// With `paint`, each element is colored by type (tuple punctuation stays plain).
+fn format_element_at(
+    el: &Element<'_>,
+    fmt: &FormatOptions,
+    indent: Option<usize>,
+    paint: bool,
+) -> String {
+    let text = match el {
+        Element::Nil => "nil".to_string(),
+        // Shown as packed(...) so it reads differently from a nested tuple;
+        // the members are formatted (and decoded again) like any tuple's.
+        Element::Bytes(b) if fmt.deep_decode && !b.is_empty() => match Element::unpack_root(b) {
+            Ok(inner @ Element::Tuple(_)) => {
+                return format!("packed{}", format_element_at(&inner, fmt, indent, paint))
+            }
+            _ => format!("{}", b),
+        },
+        Element::Bytes(b) => format!("{}", b),
+        Element::String(s) => quote_str(s),
+        Element::Tuple(items) => match indent {
+            Some(col) if items.iter().any(|it| matches!(it, Element::Tuple(_))) => {
+                let pad = " ".repeat(col + 2);
+                let mut out = String::from("(\n");
+                for it in items {
+                    out.push_str(&pad);
+                    out.push_str(&format_element_at(it, fmt, Some(col + 2), paint));
+                    out.push_str(",\n");
+                }
+                out.push_str(&" ".repeat(col));
+                out.push(')');
+                out
+            }
+            _ => {
+                let mut parts = Vec::with_capacity(items.len());
+                for it in items {
+                    parts.push(format_element_at(it, fmt, indent, paint));
+                }
+                format!("({})", parts.join(", "))
+            }
+        },
+        Element::Int(i) if fmt.int_hex => {
+            let sign = if *i < 0 { "-" } else { "" };
+            format!("{sign}0x{:x}", i.unsigned_abs())
+        }
+        Element::Int(i) => format!("{i}"),
+        Element::Float(f) => format_float(f, "f32", fmt),
+        Element::Double(d) => format_float(d, "f64", fmt),
+        Element::Bool(b) => format!("{b}"),
+        Element::Uuid(u) => format!("uuid:{u}"),
+        Element::Versionstamp(vs) if fmt.vs_hex => {
+            format!("versionstamp:{}", hex::encode(vs.as_bytes()))
+        }
+        Element::Versionstamp(vs) if !vs.is_complete() => {
+            format!("versionstamp(incomplete, user={})", vs.user_version())
+        }
+        Element::Versionstamp(vs) => {
+            // 8-byte commit version, 2-byte batch order, 2-byte user version (all big-endian).
+            let b = vs.as_bytes();
+            let version = u64::from_be_bytes(b[..8].try_into().unwrap());
+            let batch = u16::from_be_bytes([b[8], b[9]]);
+            format!(
+                "versionstamp(v={version}, batch={batch}, user={})",
+                vs.user_version()
+            )
+        }
+    };
+    if !paint {
+        return text;
+    }
+    match el {
+        Element::Tuple(_) => text,
+        Element::Nil => text.dimmed().to_string(),
+        Element::String(_) => text.green().to_string(),
+        Element::Bytes(_) => text.yellow().to_string(),
+        Element::Int(_) | Element::Float(_) | Element::Double(_) => text.blue().to_string(),
+        _ => text.magenta().to_string(),
+    }
+}
+
+fn format_float(f: &dyn std::fmt::Display, suffix: &str, fmt: &FormatOptions) -> String {
+    let suffix = if fmt.bare_floats { "" } else { suffix };
+    match fmt.precision {
+        Some(p) => format!("{f:.p$}{suffix}"),
+        None => format!("{f}{suffix}"),
+    }
+}
+
+pub fn format_key(dir: &DirectoryOutput, key: &[u8], fmt: &FormatOptions) -> String {
+    if fmt.hex_keys {
+        return format_hexdump(key, fmt.max_key_bytes, 6);
+    }
+    if fmt.raw_keys || fmt.absolute_keys {
+        return format_raw(key, fmt, fmt.max_key_bytes);
+    }
+    match dir.unpack::<Element>(key) {
+        Ok(Ok(el)) => format_element(&el, fmt),
+        _ => format_raw(key, fmt, fmt.max_key_bytes),
+    }
+}
+
+// The elements of a tuple key at `fields`, in the order given (indices past
+// the end are skipped); None for keys that aren't tuples or are shown raw.
+pub(crate) fn project_key(
+    key: &[u8],
+    fields: Option<&[usize]>,
+    schema: &[SchemaField],
+    fmt: &FormatOptions,
+) -> Option<String> {
+    if (fields.is_none() && schema.is_empty()) || fmt.raw_keys || fmt.absolute_keys || fmt.hex_keys
+    {
+        return None;
+    }
+    let Ok(Element::Tuple(items)) = Element::unpack_root(key) else {
+        return None;
+    };
+    let picked: Vec<usize> = match fields {
+        Some(fields) => fields
+            .iter()
+            .copied()
+            .filter(|&i| i < items.len())
+            .collect(),
+        None => (0..items.len()).collect(),
+    };
+    if schema.is_empty() {
+        let picked = picked.iter().map(|&i| items[i].clone()).collect();
+        return Some(format_element(&Element::Tuple(picked), fmt));
+    }
+    // Labels go by position in the whole key, so they survive --fields.
+    let parts: Vec<String> = picked
+        .iter()
+        .map(|&i| {
+            let text = format_element(&items[i], fmt);
+            match schema.get(i) {
+                Some(f) if f.kind.is_some_and(|k| k != element_kind(&items[i])) => {
+                    format!("{}?={text}", f.name)
+                }
+                Some(f) => format!("{}={text}", f.name),
+                None => text,
+            }
+        })
+        .collect();
+    Some(format!("({})", parts.join(", ")))
+}
+
+// One position of a --schema hint: a name, and optionally the element type
+// expected there.
+#[derive(Clone, Debug)]
+pub struct SchemaField {
+    pub name: String,
+    pub kind: Option<&'static str>,
+}
+
+pub(crate) const SCHEMA_TYPES: &[&str] = &[
+    "int",
+    "string",
+    "bytes",
+    "bool",
+    "float",
+    "uuid",
+    "versionstamp",
+    "tuple",
+    "nil",
+];
+
+fn element_kind(el: &Element<'_>) -> &'static str {
+    match el {
+        Element::Nil => "nil",
+        Element::Bytes(_) => "bytes",
+        Element::String(_) => "string",
+        Element::Tuple(_) => "tuple",
+        Element::Int(_) => "int",
+        Element::Float(_) | Element::Double(_) => "float",
+        Element::Bool(_) => "bool",
+        Element::Uuid(_) => "uuid",
+        Element::Versionstamp(_) => "versionstamp",
+    }
+}
+
+// Key remainder after a tuple subspace prefix, decoded as the remaining elements.
+pub(crate) fn format_subspace_key(rest: &[u8], fmt: &FormatOptions) -> String {
+    if fmt.hex_keys {
+        return format_hexdump(rest, fmt.max_key_bytes, 6);
+    }
+    match Element::unpack_root(rest) {
+        Ok(el) if !fmt.raw_keys => format_element(&el, fmt),
+        _ => format_raw(rest, fmt, fmt.max_key_bytes),
+    }
+}
+
+// A value read as `fmt.value_as`, or None when there is no hint or the bytes
+// don't fit it (wrong length, not printable UTF-8).
+fn format_value_as(val: &[u8], fmt: &FormatOptions, paint: bool) -> Option<String> {
+    let int = |le: bool| -> Option<i64> {
+        if val.is_empty() || val.len() > 8 {
+            return None;
+        }
+        let negative = (if le { val[val.len() - 1] } else { val[0] }) & 0x80 != 0;
+        let mut buf = [if negative { 0xff } else { 0 }; 8];
+        if le {
+            buf[..val.len()].copy_from_slice(val);
+            Some(i64::from_le_bytes(buf))
+        } else {
+            buf[8 - val.len()..].copy_from_slice(val);
+            Some(i64::from_be_bytes(buf))
+        }
+    };
+    let el = match fmt.value_as? {
+        ValueAs::Int => Element::Int(int(false)?),
+        ValueAs::LittleEndianInt => Element::Int(int(true)?),
+        ValueAs::Uuid => Element::Uuid(foundationdb::tuple::Uuid::from_slice(val).ok()?),
+        ValueAs::Utf8 => Element::String(printable_utf8(val)?.into()),
+        ValueAs::Hex => {
+            let max = fmt.max_value_bytes;
+            let shown = if max > 0 && val.len() > max {
+                &val[..max]
+            } else {
+                val
+            };
+            let mut text = format!("hex:{}", hex::encode(shown));
+            if shown.len() < val.len() {
+                text.push('…');
+            }
+            return Some(if paint {
+                text.yellow().to_string()
+            } else {
+                text
+            });
+        }
+    };
+    Some(format_element_at(&el, fmt, None, paint))
+}
+
+pub fn format_value(val: &[u8], fmt: &FormatOptions) -> String {
+    if let Some(text) = format_value_as(val, fmt, false) {
+        return text;
+    }
+    if fmt.hex_values {
+        return format_hexdump(val, fmt.max_value_bytes, 6);
+    }
+    match Element::unpack_root(val) {
+        Ok(el) => format_element(&el, fmt),
+        Err(_) => try_utf8_or_bytes(val, fmt, fmt.max_value_bytes),
+    }
+}
+
+// A value as printed to the terminal: decoded tuple elements colored by type
+// (strings green, bytes yellow, numbers blue, nil dimmed, the rest magenta),
+// or all green with `flat_colors`.
+pub fn paint_value(val: &[u8], fmt: &FormatOptions) -> String {
+    if fmt.flat_colors || fmt.hex_values {
+        return format_value(val, fmt).green().to_string();
+    }
+    if let Some(text) = format_value_as(val, fmt, true) {
+        return text;
+    }
+    match Element::unpack_root(val) {
+        Ok(el) => format_element_at(&el, fmt, fmt.indent(), true),
+        // Plain text comes back quoted, like a tuple string.
+        Err(_) => match try_utf8_or_bytes(val, fmt, fmt.max_value_bytes) {
+            text if text.starts_with('"') => text.green().to_string(),
+            text => text.yellow().to_string(),
+        },
+    }
+}
+
+// Classic offset/hex/ASCII dump (like `hexdump -C`), starting on a new line
+// with every line indented by `indent` columns.
+pub fn format_hexdump(b: &[u8], max: usize, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    if b.is_empty() {
+        return format!("\n{pad}(empty)");
+    }
+    let shown = if max > 0 { &b[..b.len().min(max)] } else { b };
+    let mut out = String::new();
+    for (row, chunk) in shown.chunks(16).enumerate() {
+        let mut hex = String::with_capacity(49);
+        for (idx, byte) in chunk.iter().enumerate() {
+            if idx == 8 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{byte:02x} "));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&c| {
+                if c.is_ascii_graphic() || c == b' ' {
+                    c as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!("\n{pad}{:08x}  {hex:<49} |{ascii}|", row * 16));
+    }
+    if shown.len() < b.len() {
+        out.push_str(&format!("\n{pad}… {} more bytes", b.len() - shown.len()));
+    }
+    out
+}
+
+// UTF-8 without control characters other than newlines and tabs.
+fn printable_utf8(b: &[u8]) -> Option<&str> {
+    std::str::from_utf8(b).ok().filter(|s| {
+        s.chars()
+            .all(|c| !c.is_control() || c == '\n' || c == '\r' || c == '\t')
+    })
+}
+
+pub fn try_utf8_or_bytes(b: &[u8], fmt: &FormatOptions, max: usize) -> String {
+    match printable_utf8(b) {
+        Some(s) => quote_str(s),
+        None => format_raw(b, fmt, max),
+    }
+}
+
+// Double-quoted with `"`, `\` and control characters escaped, as the tuple
+// literal parser reads strings.
+fn quote_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_ascii_control() => out.push_str(&format!("\\x{:02x}", c as u8)),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Escaped bytes, or base64 with --b64.
+pub(crate) fn format_raw(b: &[u8], fmt: &FormatOptions, max: usize) -> String {
+    if fmt.b64 {
+        format_b64(b, max)
+    } else {
+        format_bytes(b, max)
+    }
+}
+
+pub(crate) const B64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// `b64:` followed by standard padded base64; parse_bytes_literal reads it back
+// unless it was truncated (marked with a trailing `…`).
+pub fn format_b64(b: &[u8], max: usize) -> String {
+    let shown = if max > 0 && b.len() > max {
+        &b[..max]
+    } else {
+        b
+    };
+    let mut out = String::from("b64:");
+    for chunk in shown.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &c)| n | ((c as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(B64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    if shown.len() < b.len() {
+        out.push('…');
+    }
+    out
+}
+
+pub const DEFAULT_MAX_BYTES: usize = 64;
+
+pub fn format_bytes(b: &[u8], max: usize) -> String {
+    let mut out = String::new();
+    out.push_str("b\"");
+    for (idx, byte) in b.iter().enumerate() {
+        if max > 0 && idx >= max {
+            out.push('…');
+            break;
+        }
+        if *byte == b'\\' {
+            out.push_str(r"\\");
+        } else if byte.is_ascii_alphanumeric() || *byte == b'-' || *byte == b'_' {
+            out.push(*byte as char);
+        } else {
+            out.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Escaped form of a raw key that parse_key_literal reads back as the same bytes.
+pub fn key_literal(b: &[u8]) -> String {
+    let s = format_bytes(b, 0);
+    s[2..s.len() - 1].to_string()
+}
+
+// `ls` output for listings from `util::list_path`: text, one listing after
+// another, or JSON, an array of listings when the path was a glob.
+pub fn print_listings<W: Write>(
+    out: &mut W,
+    listings: &[Listing],
+    globbed: bool,
+    ls: &LsOptions,
+    fmt: FormatOptions,
+) -> Result<()> {
+    match ls.format {
+        OutputFormat::Text => {
+            for (idx, listing) in listings.iter().enumerate() {
+                if idx > 0 {
+                    writeln!(out)?;
+                }
+                print_ls_text(out, listing, ls, fmt)?;
+            }
+        }
+        OutputFormat::Json => {
+            let docs: Vec<serde_json::Value> = listings.iter().map(|l| ls_json(l, ls)).collect();
+            let doc = if globbed {
+                docs.into()
+            } else {
+                docs.into_iter().next().unwrap_or_default()
+            };
+            writeln!(out, "{}", serde_json::to_string_pretty(&doc)?)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_ls_text<W: Write>(
+    out: &mut W,
+    listing: &Listing,
+    ls: &LsOptions,
+    fmt: FormatOptions,
+) -> Result<()> {
+    let (path, dirs) = (display_path(&listing.path), &listing.dirs);
+    if listing.partition {
+        writeln!(out, "{path}: {}", "(partition)".dimmed())?;
+    } else {
+        writeln!(out, "{path}:")?;
+    }
+
+    // Directories
+    writeln!(out, "{}", "Directories:".bold())?;
+    if dirs.is_empty() {
+        writeln!(out, "(none)")?;
+    }
+    if !ls.counts && !ls.long {
+        for d in dirs {
+            let display = format!("{}/", d.name);
+            writeln!(out, "{}", display.blue().bold())?;
+        }
+    } else {
+        let prefix_label = |d: &DirEntry| match &d.prefix {
+            Some(p) => format_bytes(p, fmt.max_key_bytes),
+            None => "(partition)".to_string(),
+        };
+        let name_w = dirs
+            .iter()
+            .map(|d| d.name.chars().count() + 1)
+            .max()
+            .unwrap_or(0);
+        let prefix_w = dirs
+            .iter()
+            .map(|d| prefix_label(d).chars().count())
+            .max()
+            .unwrap_or(0);
+        for d in dirs {
+            let name = format!("{}/", d.name);
+            let mut line = format!("{}", format!("{name:<name_w$}").blue().bold());
+            if ls.counts {
+                let count = match d.count {
+                    _ if d.partition => "partition".to_string(),
+                    Some(n) if n > COUNT_CAP => format!("{COUNT_CAP}+"),
+                    Some(n) => n.to_string(),
+                    None => String::new(),
+                };
+                line.push_str(&format!(" {}", format!("{count:>8}").dimmed()));
+            }
+            if ls.long {
+                let prefix = prefix_label(d);
+                let layer = match d.layer.as_slice() {
+                    [] => "-".to_string(),
+                    // Most layers are names such as "partition".
+                    l => try_utf8_or_bytes(l, &fmt, fmt.max_value_bytes),
+                };
+                line.push_str(&format!(
+                    "  {}  {}",
+                    format!("{prefix:<prefix_w$}").yellow(),
+                    layer.magenta()
+                ));
+            }
+            writeln!(out, "{}", fit_width(line, fmt.width))?;
+        }
+    }
+
+    let Some(keys) = &listing.keys else {
+        return Ok(());
+    };
+    writeln!(out, "{}", format!("Keys (first {}):", ls.keys).bold())?;
+    for (i, k) in keys.iter().enumerate() {
+        let row = format!(
+            "{} {} {} {}",
+            format!("{:>4}.", i + 1).dimmed(),
+            k.key.cyan(),
+            "=>".dimmed(),
+            paint_value(&k.raw_value, &fmt)
+        );
+        writeln!(out, "{}", fit_width(row, fmt.width))?;
+    }
+    if keys.is_empty() {
+        writeln!(out, "(none)")?;
+    }
+    if listing.more {
+        writeln!(
+            out,
+            "{} {}",
+            "…".dimmed(),
+            format!(
+                "more than {} keys; use 'scan [limit]' or 'ls --keys <n>' to see more",
+                ls.keys
+            )
+            .dimmed()
+        )?;
+    }
+    Ok(())
+}
+
+// Directory fields beyond "name" appear only with --counts / --long; prefix,
+// layer, and raw keys/values are hex strings.
+fn ls_json(listing: &Listing, ls: &LsOptions) -> serde_json::Value {
+    let dirs: Vec<serde_json::Value> = listing
+        .dirs
+        .iter()
+        .map(|d| {
+            let mut obj = serde_json::Map::new();
+            obj.insert("name".into(), d.name.clone().into());
+            if ls.counts {
+                obj.insert("count".into(), d.count.map(|n| n.min(COUNT_CAP)).into());
+                obj.insert(
+                    "count_capped".into(),
+                    d.count.is_some_and(|n| n > COUNT_CAP).into(),
+                );
+            }
+            if ls.long {
+                obj.insert("partition".into(), d.partition.into());
+                obj.insert("prefix".into(), d.prefix.as_ref().map(hex::encode).into());
+                obj.insert("layer".into(), hex::encode(&d.layer).into());
+            }
+            obj.into()
+        })
+        .collect();
+    let mut doc = serde_json::json!({
+        "path": display_path(&listing.path),
+        "partition": listing.partition,
+        "directories": dirs,
+    });
+    if let Some(keys) = &listing.keys {
+        doc["keys"] = keys
+            .iter()
+            .map(|k| {
+                serde_json::json!({
+                    "key": k.key,
+                    "value": k.value,
+                    "key_hex": hex::encode(&k.raw_key),
+                    "value_hex": hex::encode(&k.raw_value),
+                })
+            })
+            .collect();
+        doc["more"] = listing.more.into();
+    }
+    doc
+}
+
+// `scan` output: numbered rows printed as they are read, or with
+// --format table, a table drawn once the scan completes.
+pub struct ScanPrinter<'a, W: Write> {
+    out: &'a mut W,
+    scan: &'a ScanOptions,
+    fmt: FormatOptions,
+    // Rows held for --format table: the key, and the value unless keys_only.
+    table: Vec<(String, Option<String>)>,
+}
+
+impl<'a, W: Write> ScanPrinter<'a, W> {
+    pub fn new(out: &'a mut W, scan: &'a ScanOptions, fmt: FormatOptions) -> Self {
+        Self {
+            out,
+            scan,
+            fmt,
+            table: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> ScanSink for ScanPrinter<'_, W> {
+    fn start(&mut self, path: &[String], prefix: &[u8]) -> std::io::Result<()> {
+        let (scan, fmt) = (self.scan, &self.fmt);
+        writeln!(
+            self.out,
+            "-- scanning {} at {} ({}{}{}{}{}) --",
+            display_path(path).yellow(),
+            // The directory's own prefix, to line relative keys up with absolute ones.
+            format_bytes(prefix, fmt.max_key_bytes),
+            match (scan.tail, scan.limit) {
+                (Some(n), _) => format!("last {n}"),
+                (None, Some(n)) => format!("limit {n}"),
+                (None, None) => "all".to_string(),
+            },
+            scan.prefix
+                .as_ref()
+                .map(|p| match Element::unpack_root(p) {
+                    Ok(el) if scan.subspace => format!(", subspace {}", format_element(&el, fmt)),
+                    _ => format!(", prefix {}", format_bytes(p, fmt.max_key_bytes)),
+                })
+                .unwrap_or_default(),
+            scan.after
+                .as_ref()
+                .map(|k| format!(", after {}", format_bytes(k, fmt.max_key_bytes)))
+                .unwrap_or_default(),
+            scan.begin_sel
+                .as_ref()
+                .map(|s| format!(", begin {}", s.text))
+                .unwrap_or_default(),
+            scan.end_sel
+                .as_ref()
+                .map(|s| format!(", end {}", s.text))
+                .unwrap_or_default()
+        )
+    }
+
+    fn resumed(&mut self, after: &[u8], version: i64) -> std::io::Result<()> {
+        writeln!(
+            self.out,
+            "{}",
+            format!(
+                "… continuing after {} at read version {version}",
+                format_bytes(after, self.fmt.max_key_bytes)
+            )
+            .dimmed()
+        )
+    }
+
+    fn row(&mut self, row: ScanRow) -> std::io::Result<()> {
+        let fmt = &self.fmt;
+        if self.scan.format == ScanFormat::Table {
+            let value = (!self.scan.keys_only).then(|| paint_value(&row.value, fmt));
+            self.table.push((row.key_text.cyan().to_string(), value));
+            return Ok(());
+        }
+        let line = if self.scan.keys_only {
+            format!(
+                "{} {}",
+                format!("{:>4}.", row.index).dimmed(),
+                row.key_text.cyan()
+            )
+        } else {
+            format!(
+                "{} {} {} {}",
+                format!("{:>4}.", row.index).dimmed(),
+                row.key_text.cyan(),
+                "=>".dimmed(),
+                paint_value(&row.value, fmt)
+            )
+        };
+        writeln!(self.out, "{}", fit_width(line, fmt.width))?;
+        if let Some(problem) = row.problem {
+            writeln!(self.out, "      {}", format!("! {problem}").yellow())?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, scanned: &Scanned) -> std::io::Result<()> {
+        if self.scan.format == ScanFormat::Table {
+            let rows = std::mem::take(&mut self.table);
+            print_table(self.out, &rows, self.scan.keys_only, self.fmt.width)?;
+        }
+        if let Some(cursor) = &scanned.cursor {
+            writeln!(
+                self.out,
+                "{} {}",
+                "… more; resume with".dimmed(),
+                format!("--after '{}'", key_literal(cursor)).dimmed()
+            )?;
+        }
+        if self.scan.verify_roundtrip {
+            let summary = format!(
+                "-- round-trip: {} of {} keys did not pack back to their bytes --",
+                scanned.mismatches, scanned.rows
+            );
+            let summary = if scanned.mismatches > 0 {
+                summary.yellow().to_string()
+            } else {
+                summary.dimmed().to_string()
+            };
+            writeln!(self.out, "{summary}")?;
+        }
+        Ok(())
+    }
+}
+
+// On stderr, so piped rows stay clean: what the scan returned, and whether
+// the limit cut it short.
+pub fn print_scan_summary(scanned: &Scanned) {
+    let size = crate::status::format_size;
+    eprintln!(
+        "{}",
+        format!(
+            "-- {} {}, {} of keys, {} of values{} --",
+            scanned.rows,
+            if scanned.rows == 1 { "row" } else { "rows" },
+            size(scanned.key_bytes as f64),
+            size(scanned.value_bytes as f64),
+            if scanned.cursor.is_some() {
+                ", limit reached"
+            } else {
+                ""
+            }
+        )
+        .dimmed()
+    );
+}
+
+// --timing: how long `label` took, on stderr, with where the time went.
+pub fn print_timing(label: &str, total: Duration, phases: &[(&str, Duration)]) {
+    let phases: Vec<String> = phases.iter().map(|(n, d)| format!("{n} {d:.1?}")).collect();
+    let detail = if phases.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", phases.join(", "))
+    };
+    eprintln!(
+        "{}",
+        format!("timing: {label} {total:.1?}{detail}").dimmed()
+    );
+}
+
+// A read's timing, split into its phases.
+pub fn print_read_timing(label: &str, timing: ReadTiming) {
+    print_timing(
+        label,
+        timing.transaction + timing.iterate,
+        &[
+            ("transaction", timing.transaction),
+            ("iterate", timing.iterate),
+        ],
+    );
+}
+
+// Cells narrower than this aren't cut further to fit the terminal.
+const MIN_CELL_WIDTH: usize = 8;
+
+// `scan --format table`: a bordered table sized to its widest cells. With a
+// terminal width, the value column gives way first, then the key column.
+fn print_table<W: Write>(
+    out: &mut W,
+    rows: &[(String, Option<String>)],
+    keys_only: bool,
+    width: Option<usize>,
+) -> std::io::Result<()> {
+    // Multi-line renderings (--pretty, --hex) are joined onto one line.
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(key, value)| {
+            std::iter::once(key)
+                .chain(value)
+                .map(|c| c.replace('\n', " "))
+                .collect()
+        })
+        .collect();
+    let header: &[&str] = if keys_only {
+        &["key"]
+    } else {
+        &["key", "value"]
+    };
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in &cells {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(visible_width(cell));
+        }
+    }
+    if let Some(total) = width {
+        // "│ " before each cell, " │" after the last, " │ " between.
+        let room = total.saturating_sub(3 * widths.len() + 1);
+        let mut excess = widths.iter().sum::<usize>().saturating_sub(room);
+        for w in widths.iter_mut().rev() {
+            let cut = excess.min(w.saturating_sub(MIN_CELL_WIDTH));
+            *w -= cut;
+            excess -= cut;
+        }
+    }
+    let rule = |left: &str, mid: &str, right: &str| {
+        let bars: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{left}{}{right}", bars.join(mid))
+            .dimmed()
+            .to_string()
+    };
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &w)| {
+                let cell = fit_line(cell, w);
+                let pad = w.saturating_sub(visible_width(&cell));
+                format!("{cell}{}", " ".repeat(pad))
+            })
+            .collect();
+        let bar = "│".dimmed().to_string();
+        format!("{bar} {} {bar}", cells.join(&format!(" {bar} ")))
+    };
+    writeln!(out, "{}", rule("┌", "┬", "┐"))?;
+    let header: Vec<String> = header.iter().map(|h| h.bold().to_string()).collect();
+    writeln!(out, "{}", line(&header))?;
+    writeln!(out, "{}", rule("├", "┼", "┤"))?;
+    for row in &cells {
+        writeln!(out, "{}", line(row))?;
+    }
+    writeln!(out, "{}", rule("└", "┴", "┘"))
+}
+
+// Each line of `text` cut to `width` visible columns, ending in '…' where it
+// was cut. Color sequences don't count toward the width; a cut line ends with
+// a reset so its color doesn't leak.
+pub fn fit_width(text: String, width: Option<usize>) -> String {
+    let Some(width) = width else {
+        return text;
+    };
+    text.split('\n')
+        .map(|line| fit_line(line, width.max(1)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn fit_line(line: &str, width: usize) -> String {
+    let mut visible = 0;
+    let mut in_escape = false;
+    let mut last = 0;
+    for (idx, c) in line.char_indices() {
+        if in_escape {
+            // CSI sequences end with a byte in 0x40..=0x7e.
+            in_escape = !('\x40'..='\x7e').contains(&c) || c == '[';
+            continue;
+        }
+        if c == '\x1b' {
+            in_escape = true;
+            continue;
+        }
+        visible += 1;
+        if visible == width {
+            last = idx;
+        } else if visible > width {
+            return format!("{}…\x1b[0m", &line[..last]);
+        }
+    }
+    line.to_string()
+}
+
+// Columns `text` takes on screen; color sequences take none.
+fn visible_width(text: &str) -> usize {
+    let mut visible = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        if in_escape {
+            in_escape = !('\x40'..='\x7e').contains(&c) || c == '[';
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else {
+            visible += 1;
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_path, parse_path_pattern, parse_tuple_literal};
+
+    #[test]
+    fn quoted_strings_escape_quotes_and_controls() {
+        assert_eq!(quote_str(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        assert_eq!(quote_str("a\nb\tc\r"), r#""a\nb\tc\r""#);
+        assert_eq!(
+            quote_str("nul\0bell\x07del\x7f"),
+            r#""nul\x00bell\x07del\x7f""#
+        );
+        assert_eq!(quote_str("c1\u{85}é"), r#""c1\u{85}é""#);
+    }
+
+    #[test]
+    fn formatted_strings_parse_back() {
+        let fmt = FormatOptions::default();
+        for s in [
+            "plain",
+            "quo\"te\\",
+            "\0\x01\x1b[0m\n\r\t\x7f",
+            "\u{85}\u{9f}é😀",
+            "",
+        ] {
+            let el = Element::Tuple(vec![Element::String(s.into()), Element::Int(1)]);
+            let text = format_element_at(&el, &fmt, None, false);
+            let parsed = parse_tuple_literal(&text).unwrap();
+            assert_eq!(
+                parsed,
+                vec![Element::String(s.into()), Element::Int(1)],
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn partition_listing_has_no_keys_section() {
+        let listing = Listing {
+            path: vec!["app".into(), "part".into()],
+            dirs: vec![DirEntry {
+                name: "inner".into(),
+                partition: false,
+                count: None,
+                prefix: None,
+                layer: vec![],
+            }],
+            keys: None,
+            more: false,
+            partition: true,
+            timing: Default::default(),
+        };
+        let mut out = Vec::new();
+        print_ls_text(
+            &mut out,
+            &listing,
+            &LsOptions::default(),
+            FormatOptions::default(),
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("/app/part: "), "{text}");
+        assert!(text.contains("(partition)"), "{text}");
+        assert!(text.contains("inner/"), "{text}");
+        assert!(!text.contains("Keys"), "{text}");
+    }
+
+    #[test]
+    fn display_path_round_trips() {
+        let paths: &[&[&str]] = &[
+            &[],
+            &["app", "foo"],
+            &["a/b", ""],
+            &[".", ".."],
+            &[" padded ", "tab\t"],
+            &["qu\"ote", "back\\slash", "\\\""],
+            &["é", "日本"],
+            &["a[1]", "*", "what?"],
+        ];
+        for path in paths {
+            let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+            let shown = display_path(&path);
+            assert_eq!(parse_path(&shown), path, "{shown}");
+        }
+        assert_eq!(display_path(&[]), "/");
+        assert_eq!(display_path(&["a/b".into(), "..".into()]), r#"/"a/b"/"..""#);
+        let pattern = parse_path_pattern(r#"/app/*/"a[1]""#);
+        assert_eq!(display_path_pattern(&pattern), r#"/app/*/"a[1]""#);
+        assert_eq!(parse_path_pattern(&display_path_pattern(&pattern)), pattern);
+    }
+
+    #[test]
+    fn listing_headers_quote_names() {
+        let listing = Listing {
+            path: vec!["a/b".into(), "".into()],
+            dirs: vec![],
+            keys: None,
+            more: false,
+            partition: false,
+            timing: Default::default(),
+        };
+        let mut out = Vec::new();
+        print_ls_text(
+            &mut out,
+            &listing,
+            &LsOptions::default(),
+            FormatOptions::default(),
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("/\"a/b\"/\"\":\n"), "{text}");
+    }
+
+    #[test]
+    fn deep_decode_unpacks_nested_packed_tuples() {
+        use foundationdb::tuple::pack;
+        let innermost = pack(&(2, "x"));
+        let middle = pack(&(1, Element::Bytes(innermost.into()), (3, 4)));
+        let el = Element::Tuple(vec![Element::Bytes(middle.clone().into())]);
+        let fmt = FormatOptions {
+            deep_decode: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_element_at(&el, &fmt, None, false),
+            r#"(packed(1, packed(2, "x"), (3, 4)))"#
+        );
+        // Without --deep-decode the outer bytes are shown as they are.
+        let plain = format_element_at(&el, &FormatOptions::default(), None, false);
+        assert!(!plain.contains("packed"), "{plain}");
+    }
+
+    #[test]
+    fn values_as_integers() {
+        let as_int = |value_as, val: &[u8]| {
+            let fmt = FormatOptions {
+                value_as: Some(value_as),
+                ..FormatOptions::default()
+            };
+            format_value(val, &fmt)
+        };
+        for (val, be, le) in [
+            (&b"\x00"[..], "0", "0"),
+            (b"\x7f", "127", "127"),
+            (b"\x80", "-128", "-128"),
+            (b"\xff", "-1", "-1"),
+            (b"\x01\x00", "256", "1"),
+            (b"\xff\x7f", "-129", "32767"),
+            (b"\x00\x80", "128", "-32768"),
+            (b"\xff\xff\xfe", "-2", "-65537"),
+            (
+                b"\x00\x00\x00\x00\x00\x00\x00\x2a",
+                "42",
+                "3026418949592973312",
+            ),
+            (
+                b"\x80\x00\x00\x00\x00\x00\x00\x00",
+                "-9223372036854775808",
+                "128",
+            ),
+            (
+                b"\xff\xff\xff\xff\xff\xff\xff\x7f",
+                "-129",
+                "9223372036854775807",
+            ),
+        ] {
+            assert_eq!(as_int(ValueAs::Int, val), be, "{val:?}");
+            assert_eq!(as_int(ValueAs::LittleEndianInt, val), le, "{val:?}");
+        }
+        // Empty values and ones longer than 8 bytes are shown as usual.
+        let plain = FormatOptions::default();
+        for val in [
+            &b""[..],
+            b"\x01\x02\x03\x04\x05\x06\x07\x08\x09",
+            b"nine byte",
+        ] {
+            for value_as in [ValueAs::Int, ValueAs::LittleEndianInt] {
+                assert_eq!(as_int(value_as, val), format_value(val, &plain), "{val:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fit_width_cuts_lines_to_the_width() {
+        let fit = |text: &str, width| fit_width(text.to_string(), width);
+        assert_eq!(fit("abcdef", Some(6)), "abcdef");
+        assert_eq!(fit("abcdefg", Some(6)), "abcde…\x1b[0m");
+        assert_eq!(fit("abc\nabcdefg\n", Some(4)), "abc\nabc…\x1b[0m\n");
+        assert_eq!(fit("abc", Some(0)), "…\x1b[0m");
+        // Multi-byte characters are one column each and never split.
+        assert_eq!(fit("héllo wörld", Some(11)), "héllo wörld");
+        assert_eq!(fit("héllo wörld", Some(8)), "héllo w…\x1b[0m");
+        assert_eq!(fit("ééé", Some(2)), "é…\x1b[0m");
+        // Color sequences take no columns.
+        let red = "\x1b[31mré\x1b[0m";
+        assert_eq!(fit(&format!("{red}{red}"), Some(4)), format!("{red}{red}"));
+        assert_eq!(
+            fit(&format!("{red}{red}"), Some(3)),
+            format!("{red}\x1b[31m…\x1b[0m")
+        );
+        // No width: printed whole.
+        let long = "ü".repeat(500);
+        assert_eq!(fit(&long, None), long);
+    }
+}
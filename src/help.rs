@@ -0,0 +1,493 @@
+// REPL command reference: `help` prints the summaries, `help <command>` one entry.
+
+pub struct CommandHelp {
+    // The first name is the one listed; the rest are aliases.
+    pub names: &'static [&'static str],
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub details: &'static [&'static str],
+    pub example: &'static str,
+}
+
+const FORMAT_FLAGS: &[&str] = &[
+    "--pretty           Break nested tuples across indented lines",
+    "--hex, --hex-keys  Show values/keys as offset+hex+ASCII dumps",
+    "--int-hex          Show tuple integers in hexadecimal",
+    "--b64              Show undecodable bytes as b64:... instead of \\xHH",
+    "--max-bytes <n>    Truncate byte output after n bytes (0 = all)",
+    "--vs-hex           Show versionstamps as raw hex",
+    "--precision <n>    Show floats/doubles with n decimals",
+    "--bare-floats      Omit the f32/f64 suffix on floats/doubles",
+    "--flat-colors      Print values all green instead of colored by type",
+    "--absolute-keys    Show full keys, directory prefix included, as escaped bytes",
+    "--max-key-bytes <n>, --max-value-bytes <n>  Same as --max-bytes, for one side",
+    "--deep-decode      Show byte strings holding packed tuples as packed(...)",
+    "--value-as <type>  Read values as int, uuid, utf8, hex, or little-endian-int",
+    "--full             Print whole rows instead of cutting them to the terminal width",
+];
+
+pub const COMMANDS: &[CommandHelp] = &[
+    CommandHelp {
+        names: &["help"],
+        usage: "help [command]",
+        summary: "Show this help, or details for one command",
+        details: &[],
+        example: "help scan",
+    },
+    CommandHelp {
+        names: &["exit", "quit"],
+        usage: "exit | quit",
+        summary: "Exit the REPL",
+        details: &[],
+        example: "exit",
+    },
+    CommandHelp {
+        names: &["pwd"],
+        usage: "pwd",
+        summary: "Print current directory path",
+        details: &["--layers           List each directory down to here with its layer"],
+        example: "pwd",
+    },
+    CommandHelp {
+        names: &["cd"],
+        usage: "cd [path] [--layer <bytes>] [--create]",
+        summary: "Change directory (use /, .., -, or relative)",
+        details: &[
+            "The directory must exist unless --create is given",
+            "With no path, go to the root; 'cd -' returns to the previous directory",
+            "--create           Create the directory (and parents) if missing; asks first unless --yes",
+            "--layer <bytes>    Fail unless the directory has this layer (with --create, the new one's layer)",
+        ],
+        example: "cd /app/users",
+    },
+    CommandHelp {
+        names: &["ls"],
+        usage: "ls [path]",
+        summary: "List subdirectories at path (default: current)",
+        details: &[
+            "--counts           Show key counts per subdirectory (exact up to 10,000)",
+            "--sort <order>     name, name-desc, or size (largest first; needs --counts)",
+            "--sort prefix, --by-prefix  Order by allocated prefix, roughly creation order",
+            "-l, --long         Show each subdirectory's prefix and layer",
+            "--keys <n>         Preview n keys (default 50; 0 = directories only)",
+            "--format <fmt>     text (default) or json",
+            "--layer <bytes>    Fail unless the directory has this layer",
+            "--parallel <n>     Open up to n subdirectories at once for --counts/-l (default 16)",
+            "--no-snapshot      Use serializable instead of snapshot reads",
+            "Also accepts the scan formatting flags (--pretty, --hex, ...)",
+            "Path segments may be globs (*, ?, [abc]): ls /app/*/config lists each match",
+            "Quote a segment to match it literally: ls /app/\"a[1]\"",
+        ],
+        example: "ls -l --counts /app",
+    },
+    CommandHelp {
+        names: &["scan", "dump"],
+        usage: "scan [limit] [pfx]",
+        summary: "Print key=>value pairs in current dir (default 50)",
+        details: &[
+            "pfx: raw bytes like '\\x01ab' or 'b64:AWFi', or a tuple like '(\"users\", 42)'",
+            "--limit <n>, -n    Same as a bare number; must be at least 1",
+            "--all              Stream every key=>value pair",
+            "--tail <n>         Show the last n pairs (still in key order)",
+            "Past the 5s transaction limit, continues after the last key in a new transaction",
+            "--prefix <pfx>, -p Same as a bare pfx",
+            "--prefix-tuple     Read pfx as one tuple element: users means (\"users\",)",
+            "--after <key>      Start just past key (a cursor printed by a cut-off scan)",
+            "--begin-sel <sel>, --end-sel <sel>  Bound the scan with key selectors, e.g. firstGreaterThan:abc",
+            "--raw, -r          Show keys as escaped bytes without tuple decoding",
+            "--fields <i,j>     Show only these elements of tuple keys, e.g. 0,2",
+            "--schema <spec>    Label tuple key positions, e.g. 'idx:int, name:string'",
+            "--verify-roundtrip Flag keys that don't pack back from their decoded tuple",
+            "--format table     Draw a bordered key/value table instead of numbered rows",
+            "--layer <bytes>    Fail unless the directory has this layer",
+            "--no-snapshot      Use serializable instead of snapshot reads",
+            FORMAT_FLAGS[0],
+            FORMAT_FLAGS[1],
+            FORMAT_FLAGS[2],
+            FORMAT_FLAGS[3],
+            FORMAT_FLAGS[4],
+            FORMAT_FLAGS[5],
+            FORMAT_FLAGS[6],
+            FORMAT_FLAGS[7],
+            FORMAT_FLAGS[8],
+            FORMAT_FLAGS[9],
+            FORMAT_FLAGS[10],
+            FORMAT_FLAGS[11],
+            FORMAT_FLAGS[12],
+            FORMAT_FLAGS[13],
+        ],
+        example: "scan 100 '(\"users\",)' --pretty",
+    },
+    CommandHelp {
+        names: &["subspace"],
+        usage: "subspace <tuple> [n]",
+        summary: "Scan a tuple subspace of the current dir, keys shown relative to it",
+        details: &[
+            "Keys print with the subspace's elements stripped; 'more' continues",
+            "Accepts the scan flags (--limit, --all, --after, --raw, --pretty, ...)",
+        ],
+        example: "subspace '(\"users\", 42)' 20",
+    },
+    CommandHelp {
+        names: &["keys"],
+        usage: "keys [limit] [pfx]",
+        summary: "Print only the keys in current dir (default 50)",
+        details: &[
+            "Values are not rendered, though FoundationDB still returns them",
+            "Accepts the scan flags (--limit, --all, --prefix, --after, --raw, ...); 'more' continues",
+        ],
+        example: "keys --prefix '(\"users\",)' --all",
+    },
+    CommandHelp {
+        names: &["rawscan"],
+        usage: "rawscan <beg> [end]",
+        summary: "Print key=>value pairs in an absolute key range",
+        details: &[
+            "Bypasses the directory layer; keys are absolute and shown as raw bytes",
+            "end defaults to \\xff (\\xff\\xff with --system)",
+            "--limit <n>, -n    Stop after n pairs (default 50)",
+            "--all              Stream every key=>value pair in the range",
+            "--system           Allow reading system keys (\\xff...)",
+            "--no-snapshot      Use serializable instead of snapshot reads",
+            FORMAT_FLAGS[0],
+            FORMAT_FLAGS[1],
+            FORMAT_FLAGS[2],
+            FORMAT_FLAGS[3],
+            FORMAT_FLAGS[4],
+            FORMAT_FLAGS[5],
+            FORMAT_FLAGS[6],
+            FORMAT_FLAGS[7],
+            FORMAT_FLAGS[8],
+            FORMAT_FLAGS[10],
+            FORMAT_FLAGS[11],
+            FORMAT_FLAGS[12],
+            FORMAT_FLAGS[13],
+        ],
+        example: "rawscan '\\xff/conf/' '\\xff/conf0' --system",
+    },
+    CommandHelp {
+        names: &["more"],
+        usage: "more",
+        summary: "Continue the previous scan where it stopped",
+        details: &[],
+        example: "more",
+    },
+    CommandHelp {
+        names: &["get", "cat"],
+        usage: "get|cat <key>",
+        summary: "Print one value (bytes or tuple key) in current dir",
+        details: FORMAT_FLAGS,
+        example: "get '(\"users\", 42)'",
+    },
+    CommandHelp {
+        names: &["watch"],
+        usage: "watch <key>",
+        summary: "Wait for a key (bytes or tuple) to change; Ctrl-C cancels",
+        details: &[],
+        example: "watch counter",
+    },
+    CommandHelp {
+        names: &["watch-dir"],
+        usage: "watch-dir [path]",
+        summary: "Print a timestamped line whenever any key in path changes; Ctrl-C cancels",
+        details: &[
+            "Checks once a second; several writes within one check show as one change",
+            "Subdirectories are not watched",
+        ],
+        example: "watch-dir /app/jobs",
+    },
+    CommandHelp {
+        names: &["version"],
+        usage: "version",
+        summary: "Print the current read version",
+        details: &[],
+        example: "version",
+    },
+    CommandHelp {
+        names: &["setversion"],
+        usage: "setversion <v>",
+        summary: "Pin reads to version v for subsequent ls/scan",
+        details: &["FoundationDB keeps about 5 seconds of history; older versions fail"],
+        example: "setversion 123456789",
+    },
+    CommandHelp {
+        names: &["clearversion"],
+        usage: "clearversion",
+        summary: "Stop pinning the read version",
+        details: &[],
+        example: "clearversion",
+    },
+    CommandHelp {
+        names: &["env", "status"],
+        usage: "env",
+        summary: "Show the cluster file, API version, TLS use, and database health",
+        details: &["Read from the cluster's \\xff\\xff/status/json special key"],
+        example: "env",
+    },
+    CommandHelp {
+        names: &["cluster-status"],
+        usage: "cluster-status [--raw]",
+        summary: "Summarize cluster health, processes, data distribution, and workload",
+        details: &["--raw              Print the full status JSON"],
+        example: "cluster-status",
+    },
+    CommandHelp {
+        names: &["workers"],
+        usage: "workers",
+        summary: "List worker processes with their addresses, classes, and roles",
+        details: &[
+            "Addresses come from the \\xff\\xff/worker_interfaces/ special keys",
+            "Classes and roles come from the status document",
+        ],
+        example: "workers",
+    },
+    CommandHelp {
+        names: &["begin"],
+        usage: "begin",
+        summary: "Open a read transaction; ls/scan/get/more share it until 'end'",
+        details: &[
+            "Starts at the pinned version if one is set; the prompt shows [txn@v]",
+            "Reads fail with transaction_too_old once it is about 5 seconds old",
+        ],
+        example: "begin",
+    },
+    CommandHelp {
+        names: &["end", "rollback"],
+        usage: "end",
+        summary: "Close the transaction opened by 'begin'",
+        details: &[],
+        example: "end",
+    },
+    CommandHelp {
+        names: &["export"],
+        usage: "export [path] [-o f]",
+        summary: "Dump key-values (hex, relative keys) to stdout or file f",
+        details: &["--parallel <n>     Read up to n shards concurrently (output stays ordered)"],
+        example: "export /app/users -o users.dump",
+    },
+    CommandHelp {
+        names: &["export-tree"],
+        usage: "export-tree [path] [-o f]",
+        summary: "Write the directory hierarchy (no keys) as JSON to stdout or file f",
+        details: &[
+            "Each directory has name, path, partition, prefix and layer (hex), and children",
+            "--depth <n>        Do not descend more than n levels; those have no children field",
+        ],
+        example: "export-tree /app --depth 2 -o layout.json",
+    },
+    CommandHelp {
+        names: &["import"],
+        usage: "import <f> [path]",
+        summary: "Load a dump into path (default: current), creating it",
+        details: &[
+            "--skip-existing    Leave existing keys untouched (default replaces them; asks first)",
+            "--dry-run          Print what would change without writing",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "import users.dump /app/users-copy --skip-existing",
+    },
+    CommandHelp {
+        names: &["load"],
+        usage: "load <f> [path]",
+        summary: "Write key<TAB>value lines (byte or tuple literals) into path (default: current)",
+        details: &[
+            "Lines starting with '#' and blank lines are skipped",
+            "The whole file is checked before anything is written",
+            "--clear-first      Clear the directory's keys first (same transaction as the open)",
+            "--dry-run          Check the file and report what would change",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "load fixtures.tsv --clear-first",
+    },
+    CommandHelp {
+        names: &["cp"],
+        usage: "cp <src> <dst>",
+        summary: "Copy key-values into dst, creating it; asks first",
+        details: &[
+            "--layer <bytes>    Layer for dst (default: the source's layer)",
+            "--dry-run          Print what would change without writing",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "cp /app/users /app/users-backup",
+    },
+    CommandHelp {
+        names: &["diff"],
+        usage: "diff <a> <b>",
+        summary: "Compare two directories' key-values, keys relative to each",
+        details: &[
+            "Prints how many keys are only in a (-), only in b (+), or differ (~)",
+            "--verbose, -v      Also print each differing key and its value(s)",
+            "Accepts the scan formatting flags (--pretty, --hex, ...)",
+        ],
+        example: "diff /app/users /app/users-backup -v",
+    },
+    CommandHelp {
+        names: &["find"],
+        usage: "find <glob> [path]",
+        summary: "Find directories by name under path",
+        details: &["--max-depth <n>    Do not descend more than n levels"],
+        example: "find 'user*' /app --max-depth 2",
+    },
+    CommandHelp {
+        names: &["grep"],
+        usage: "grep <regex> [path]",
+        summary: "Print key=>value pairs under path and its subdirectories whose value matches",
+        details: &[
+            "Values are matched as scan would show them, before --max-bytes truncation",
+            "--limit <n>, -n    Stop after n matches (default 50)",
+            "--all              Print every match",
+            "--ignore-case, -i  Match without regard to case",
+        ],
+        example: "grep -i 'alice@' /app",
+    },
+    CommandHelp {
+        names: &["groupby"],
+        usage: "groupby [path]",
+        summary: "Count keys by the first element of their tuple, as a histogram",
+        details: &[
+            "--limit <n>, -n    Read at most n keys (default 10000)",
+            "--all              Read every key",
+            "Keys that are not tuples are counted together as (not a tuple)",
+        ],
+        example: "groupby /app --all",
+    },
+    CommandHelp {
+        names: &["exists"],
+        usage: "exists <path>",
+        summary: "Print whether a directory exists (true/false)",
+        details: &[],
+        example: "exists /app/foo",
+    },
+    CommandHelp {
+        names: &["find-prefix"],
+        usage: "find-prefix <bytes>",
+        summary: "Print the directory a raw key or prefix belongs to, with its prefix and layer",
+        details: &["Reads the directory layer's metadata from the root down, one level at a time"],
+        example: "find-prefix '\\x15\\x07\\x02user'",
+    },
+    CommandHelp {
+        names: &["mkdir"],
+        usage: "mkdir <path>",
+        summary: "Create a directory and any missing parents",
+        details: &["--layer <bytes>    Layer for the new directory"],
+        example: "mkdir /app/new",
+    },
+    CommandHelp {
+        names: &["rmdir", "rm"],
+        usage: "rmdir|rm <path>",
+        summary: "Remove a directory and all its contents; asks first",
+        details: &[
+            "--dry-run          List the directories and key ranges that would go",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "rmdir /app/old --dry-run",
+    },
+    CommandHelp {
+        names: &["clear-prefix"],
+        usage: "clear-prefix <pfx>",
+        summary: "Clear every key under a prefix in the current dir; asks first",
+        details: &[
+            "pfx: raw bytes like '\\x01ab' or a tuple like '(\"users\", 42)'",
+            "--dry-run          Print the key range, key count, and estimated size",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "clear-prefix '(\"sessions\",)' --dry-run",
+    },
+    CommandHelp {
+        names: &["mv"],
+        usage: "mv <src> <dst>",
+        summary: "Move or rename a directory; asks first",
+        details: &[
+            "--dry-run          Check the move without committing it",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "mv /app/new /app/renamed",
+    },
+    CommandHelp {
+        names: &["rename"],
+        usage: "rename <newname>",
+        summary: "Rename the current directory in place and stay in it; asks first",
+        details: &[
+            "Keeps the parent; quote the name to include '/' (\"a/b\")",
+            "--dry-run          Check the rename without committing it",
+            "--yes, -y          Do not ask for confirmation",
+        ],
+        example: "rename users_v2",
+    },
+    CommandHelp {
+        names: &["set"],
+        usage: "set <opt> <value>",
+        summary: "Change a session setting; the REPL saves it for next time",
+        details: &[
+            "color on|off       Colored output (default on unless NO_COLOR is set)",
+            "value-colors typed|flat  Color values by element type, or all green (default typed)",
+            "confirm on|off     Ask before destructive commands (default on)",
+            "timing on|off      Print each command's duration on stderr (default off)",
+            "pager on|off       Page ls/scan/keys/rawscan/more/grep/groupby/find output (default off)",
+            "limit <n>          Default row limit for scan, keys, rawscan, grep (default 50)",
+            "max-bytes <n>      Truncate long byte strings, 0 = never (default 64)",
+            "streaming <mode>   auto, want-all, iterator, exact, small, medium, large, serial",
+            "max-scan-bytes <size>|off  Refuse --all scans and exports estimated over size, e.g. 1GB",
+        ],
+        example: "set limit 200",
+    },
+    CommandHelp {
+        names: &["show"],
+        usage: "show",
+        summary: "Print the current settings",
+        details: &[],
+        example: "show",
+    },
+];
+
+pub fn print_overview() {
+    println!("Commands (Ctrl-R searches history):");
+    for cmd in COMMANDS {
+        println!("  {:<20} {}", cmd.usage, cmd.summary);
+    }
+    println!("Type 'help <command>' for flags and an example.");
+}
+
+pub fn print_command(name: &str) {
+    let Some(cmd) = COMMANDS.iter().find(|c| c.names.contains(&name)) else {
+        match closest(name) {
+            Some(s) => println!("Unknown command: {name}. Did you mean '{s}'?"),
+            None => println!("Unknown command: {name}. Try 'help'."),
+        }
+        return;
+    };
+    println!("Usage: {}", cmd.usage);
+    println!("  {}", cmd.summary);
+    if cmd.names.len() > 1 {
+        println!("  Aliases: {}", cmd.names[1..].join(", "));
+    }
+    for line in cmd.details {
+        println!("  {line}");
+    }
+    println!("Example: {}", cmd.example);
+}
+
+// Nearest command name by edit distance, if it is close enough to be a typo.
+pub fn closest(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .flat_map(|c| c.names.iter().copied())
+        .map(|n| (edit_distance(name, n), n))
+        .filter(|(d, _)| *d <= (name.chars().count() / 2).max(1))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, n)| n)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != *cb);
+            cur.push(sub.min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
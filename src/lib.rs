@@ -0,0 +1,21 @@
+//! Helpers behind the `fdbdir` CLI, usable on their own: open directories
+//! by path, scan and list them, and parse or format tuple keys the way the
+//! CLI does.
+//!
+//! - [`parse`]: paths, byte and tuple literals, selectors, schemas.
+//! - [`format`]: rendering keys, values, and paths.
+//! - [`util`]: directory lookup, transactions, ls, scan, and the other commands,
+//!   each run against a [`util::Client`]: a database plus the options (directory
+//!   layer, transaction tag and options) its transactions share.
+//!
+//! Commands return what they read; printing summaries and timings is left to
+//! the caller ([`format::print_scan_summary`], [`format::print_timing`]).
+
+pub mod config;
+pub mod dump;
+pub mod format;
+pub mod options;
+pub mod output;
+pub mod parse;
+pub mod status;
+pub mod util;
@@ -1,17 +1,74 @@
+mod help;
 mod repl;
-mod util;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use fdbdir::{config, dump, format, options, output, parse, status, util};
+
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use foundationdb::options::{NetworkOption, TransactionOption};
+use std::io::{IsTerminal, Write};
 
 /// FoundationDB Directory Explorer CLI
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
+#[command(group(ArgGroup::new("batch").args(["script", "exec_file"])))]
 struct Cli {
+    /// Connection profile from ~/.config/fdbdir/config.toml; other flags override its values
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
     /// Path to cluster file (defaults to platform default)
     #[arg(long)]
     cluster_file: Option<String>,
 
+    /// Connection string like desc:id@10.0.0.1:4500, in place of a cluster file
+    #[arg(long, value_name = "STRING", conflicts_with = "cluster_file", value_parser = options::parse_cluster_string)]
+    cluster_string: Option<String>,
+
+    /// TLS certificate file
+    #[arg(long, value_name = "PATH")]
+    tls_cert_file: Option<String>,
+
+    /// TLS private key file
+    #[arg(long, value_name = "PATH")]
+    tls_key_file: Option<String>,
+
+    /// TLS CA bundle used to verify peers
+    #[arg(long, value_name = "PATH")]
+    tls_ca_file: Option<String>,
+
+    /// FoundationDB API version to request (default: the newest this build supports)
+    #[arg(long, value_name = "N")]
+    api_version: Option<i32>,
+
+    /// Database option to set after connecting, e.g. transaction_timeout=5000 (repeatable)
+    #[arg(long = "db-option", value_name = "NAME[=VALUE]", value_parser = options::parse_database_option)]
+    db_options: Vec<foundationdb::options::DatabaseOption>,
+
+    /// Transaction option for ls/scan/get/rawscan reads, e.g. priority_batch or tag=explore (repeatable)
+    #[arg(long = "tr-option", value_name = "NAME[=VALUE]", value_parser = options::parse_transaction_option)]
+    tr_options: Vec<foundationdb::options::TransactionOption>,
+
+    /// Tag every transaction with NAME (at most 16 bytes) so operators can identify and throttle it
+    #[arg(long, value_name = "NAME", value_parser = options::parse_tag)]
+    tag: Option<String>,
+
+    /// Run reads at batch priority so they yield to production traffic (may be throttled under load)
+    #[arg(long)]
+    batch_priority: bool,
+
+    /// Ask storage servers to serve reads after normal-priority ones (needs API version 730+)
+    #[arg(long)]
+    low_read_priority: bool,
+
+    /// Prefix of the directory layer's node subspace (default: \xFE, or the content prefix + \xFE)
+    #[arg(long, value_name = "BYTES")]
+    node_prefix: Option<String>,
+
+    /// Prefix under which the directory layer allocates directory contents (default: none)
+    #[arg(long, value_name = "BYTES")]
+    content_prefix: Option<String>,
+
     /// Start in interactive (REPL) mode
     #[arg(long, short = 'i')]
     interactive: bool,
@@ -20,6 +77,42 @@ struct Cli {
     #[arg(long)]
     no_connect: bool,
 
+    /// Run REPL commands read from stdin, one per line, without prompts or colors
+    #[arg(long, conflicts_with = "interactive")]
+    script: bool,
+
+    /// Run REPL commands from a file, one per line ('#' comments and blank lines are skipped)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["interactive", "script"])]
+    exec_file: Option<String>,
+
+    /// With --script/--exec-file, run every command even after one fails (still exits non-zero)
+    #[arg(long, requires = "batch")]
+    keep_going: bool,
+
+    /// Write command output to a file instead of stdout (colors are disabled)
+    #[arg(long, short = 'o', global = true, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Print how long connecting and each command took (on stderr)
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Report each transaction retry and the error that caused it (on stderr)
+    #[arg(long, global = true)]
+    verbose_retries: bool,
+
+    /// REPL history file (default: $FDBDIR_HISTORY, else fdbdir/history in the user data directory)
+    #[arg(long, value_name = "PATH", conflicts_with = "no_history")]
+    history_file: Option<String>,
+
+    /// Don't load or save REPL history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Page a command's output through $PAGER (default less) when stdout is a terminal
+    #[arg(long, global = true)]
+    pager: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -28,43 +121,622 @@ struct Cli {
 enum Commands {
     /// List subdirectories at a path
     Ls {
-        /// Directory path like /app/foo (root if omitted)
+        /// Directory path like /app/foo (root if omitted); segments may be globs like /app/*/config
         path: Option<String>,
+        /// Show the number of keys in each subdirectory (exact up to 10,000)
+        #[arg(long)]
+        counts: bool,
+        /// Order subdirectories by name, reverse name, key count (size needs --counts),
+        /// or allocated prefix
+        #[arg(long, value_enum)]
+        sort: Option<util::LsSort>,
+        /// Same as --sort prefix: roughly creation order
+        #[arg(long, conflicts_with = "sort")]
+        by_prefix: bool,
+        /// Also show each subdirectory's allocated prefix and layer
+        #[arg(long, short = 'l')]
+        long: bool,
+        /// Number of keys to preview (0 lists directories only)
+        #[arg(long, default_value_t = 50)]
+        keys: usize,
+        /// Output format; json prints one object with directories and sampled keys
+        #[arg(long = "format", value_enum, default_value_t = util::OutputFormat::Text)]
+        output_format: util::OutputFormat,
+        /// Fail unless the directory has this layer (supports \xHH escapes)
+        #[arg(long)]
+        layer: Option<String>,
+        /// Open up to N subdirectories at once for --counts, --long, and --sort prefix
+        #[arg(long, value_name = "N", default_value_t = util::LS_PARALLEL as u16, value_parser = clap::value_parser!(u16).range(1..))]
+        parallel: u16,
+        /// Exit with status 1 when no directories or keys are listed
+        #[arg(long)]
+        fail_on_empty: bool,
+        #[command(flatten)]
+        read: ReadArgs,
+        #[command(flatten)]
+        format: FormatArgs,
     },
     /// Scan key-values within a directory
     Scan {
         /// Directory path like /app/foo (root if omitted)
         path: Option<String>,
-        /// Limit number of kv pairs
+        /// Limit number of kv pairs (must be at least 1)
         #[arg(long, short = 'n', default_value_t = 50)]
         limit: usize,
-        /// Optional raw byte prefix (supports \xHH escapes)
+        /// Stream every key-value in the directory (no limit)
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+        /// Show the last N key-values instead of the first (read backwards, printed in key order)
+        #[arg(long, value_name = "N", conflicts_with_all = ["limit", "all"], value_parser = clap::value_parser!(u64).range(1..))]
+        tail: Option<u64>,
+        /// Optional prefix: raw bytes (supports \xHH escapes) or a tuple literal like ("users", 42)
         #[arg(long, short = 'p')]
         prefix: Option<String>,
+        /// Read --prefix as one tuple element (e.g. users for ("users",)) instead of raw bytes
+        #[arg(long, requires = "prefix")]
+        prefix_tuple: bool,
+        /// Start just past this key (relative to the directory), e.g. a cursor printed by a previous scan
+        #[arg(long, value_name = "KEY")]
+        after: Option<String>,
+        /// Start at a key selector, e.g. firstGreaterThan:("users", 42) (see README for the grammar)
+        #[arg(long, value_name = "SELECTOR", conflicts_with = "after")]
+        begin_sel: Option<String>,
+        /// End (exclusive) at a key selector, e.g. firstGreaterOrEqual+10:abc
+        #[arg(long, value_name = "SELECTOR")]
+        end_sel: Option<String>,
         /// Do not attempt tuple parsing for keys
         #[arg(long, short = 'r')]
         raw: bool,
+        /// Print keys only, without values
+        #[arg(long)]
+        keys_only: bool,
+        /// Show only these elements of tuple keys, by index (e.g. 0,2)
+        #[arg(long, value_name = "I,J,...")]
+        fields: Option<String>,
+        /// Name the positions of tuple keys in the output, with optional types (e.g. "idx:int, name:string")
+        #[arg(long, value_name = "NAME[:TYPE],...")]
+        schema: Option<String>,
+        /// Warn about keys whose decoded tuple does not pack back to the same bytes
+        #[arg(long)]
+        verify_roundtrip: bool,
+        /// With --all, refuse to scan when the directory's estimated size exceeds this (e.g. 500MB)
+        #[arg(long, value_name = "SIZE", value_parser = parse::parse_byte_size)]
+        max_scan_bytes: Option<u64>,
+        /// Row layout; table draws a bordered key/value table once the rows are read (needs a limit)
+        #[arg(long = "format", value_enum, default_value_t = util::ScanFormat::List)]
+        output_format: util::ScanFormat,
+        /// Fail unless the directory has this layer (supports \xHH escapes)
+        #[arg(long)]
+        layer: Option<String>,
+        /// Exit with status 1 when no key-values are found
+        #[arg(long)]
+        fail_on_empty: bool,
+        #[command(flatten)]
+        read: ReadArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Scan an absolute key range, bypassing the directory layer
+    Rawscan {
+        /// Begin key (inclusive): raw bytes (supports \xHH escapes) or a tuple literal
+        begin: String,
+        /// End key (exclusive); defaults to \xff, or \xff\xff with --system
+        end: Option<String>,
+        /// Limit number of kv pairs (must be at least 1)
+        #[arg(long, short = 'n', default_value_t = 50)]
+        limit: usize,
+        /// Stream every key-value in the range (no limit)
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+        /// Allow reading system keys (\xff...)
+        #[arg(long)]
+        system: bool,
+        #[command(flatten)]
+        read: ReadArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Print the current read version (for use with --at-version)
+    Version,
+    /// Show the cluster file, API version, TLS use, and database health
+    Env,
+    /// Summarize cluster health, processes, data distribution, and workload
+    ClusterStatus {
+        /// Print the full status JSON instead of a summary
+        #[arg(long)]
+        raw: bool,
+    },
+    /// List the worker processes registered with the cluster, with their classes and roles
+    Workers,
+    /// Dump all key-values of a directory (hex, keys relative to the directory; use -o to write a file)
+    Export {
+        /// Directory path like /app/foo
+        path: String,
+        /// Read up to N shards of the directory concurrently (1 = serial)
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        parallel: u16,
+        /// Refuse to export when the directory's estimated size exceeds this (e.g. 2GB)
+        #[arg(long, value_name = "SIZE", value_parser = parse::parse_byte_size)]
+        max_scan_bytes: Option<u64>,
+    },
+    /// Write the directory hierarchy (names, paths, prefixes, layers; no keys) as JSON
+    ExportTree {
+        /// Directory to start from (root if omitted)
+        path: Option<String>,
+        /// Do not descend more than this many levels below the starting directory
+        #[arg(long)]
+        depth: Option<usize>,
+    },
+    /// Load a dump produced by `export` into a directory (created if missing)
+    Import {
+        /// Dump file ('-' for stdin)
+        file: String,
+        /// Target directory path like /app/bar
+        path: String,
+        /// Leave keys that already exist untouched (default: overwrite them, after asking)
+        #[arg(long)]
+        skip_existing: bool,
+        /// Print what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Write key-values from a file of `key<TAB>value` lines (byte or tuple literals) into a directory
+    Load {
+        /// File to read ('-' for stdin)
+        file: String,
+        /// Target directory path like /app/fixtures (created if missing)
+        path: String,
+        /// Clear the directory's keys before loading
+        #[arg(long)]
+        clear_first: bool,
+        /// Check the file and print what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Copy all key-values of a directory into another (created if missing)
+    Cp {
+        /// Source directory path
+        src: String,
+        /// Destination directory path
+        dst: String,
+        /// Layer for the destination (defaults to the source's layer; supports \xHH escapes)
+        #[arg(long)]
+        layer: Option<String>,
+        /// Print what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Compare two directories' key-values; exit status 1 if they differ
+    Diff {
+        /// First directory path (its keys are the '-' side)
+        a: String,
+        /// Second directory path (its keys are the '+' side)
+        b: String,
+        /// Print every differing key, not only the counts
+        #[arg(long, short = 'v')]
+        verbose: bool,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Recursively find directories whose name matches a glob (e.g. 'user*')
+    Find {
+        /// Shell-style glob matched against directory names
+        pattern: String,
+        /// Directory to search under (root if omitted)
+        path: Option<String>,
+        /// Do not descend more than this many levels below the starting directory
+        #[arg(long)]
+        max_depth: Option<usize>,
+    },
+    /// Print key-values under a directory subtree whose decoded value matches a regex
+    Grep {
+        /// Regular expression matched against each decoded value
+        pattern: String,
+        /// Directory to search under (root if omitted)
+        path: Option<String>,
+        /// Stop after this many matches
+        #[arg(long, short = 'n', default_value_t = 50)]
+        limit: usize,
+        /// Print every match (no limit)
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+        /// Match without regard to case
+        #[arg(long, short = 'i')]
+        ignore_case: bool,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Count a directory's keys grouped by the first element of their tuple
+    Groupby {
+        /// Directory path like /app/foo (root if omitted)
+        path: Option<String>,
+        /// Read at most this many keys
+        #[arg(long, short = 'n', default_value_t = dump::GROUPBY_LIMIT)]
+        limit: usize,
+        /// Read every key in the directory
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Check whether a directory exists: exit 0 if it does, 1 if not
+    Exists {
+        /// Directory path like /app/foo
+        path: String,
+        /// Print nothing; only set the exit status
+        #[arg(long, short = 'q')]
+        quiet: bool,
+    },
+    /// Print the directory a raw key or prefix belongs to, with its prefix and layer
+    FindPrefix {
+        /// Raw key bytes (supports \xHH escapes), e.g. a key copied from a log
+        bytes: String,
+    },
+    /// Create a directory (parents are created as needed)
+    Mkdir {
+        /// Directory path like /app/foo
+        path: String,
+        /// Layer for the new directory (supports \xHH escapes)
+        #[arg(long)]
+        layer: Option<String>,
+    },
+    /// Remove a directory with all of its subdirectories and keys
+    Rm {
+        /// Directory path like /app/foo
+        path: String,
+        /// Print what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Clear every key under a prefix within a directory
+    ClearPrefix {
+        /// Directory path like /app/foo
+        path: String,
+        /// Prefix relative to the directory: raw bytes (supports \xHH escapes) or a tuple literal
+        prefix: String,
+        /// Print the range and its estimated size without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
     },
+    /// Move or rename a directory (keys keep their prefix)
+    Mv {
+        /// Existing directory path
+        src: String,
+        /// New directory path
+        dst: String,
+        /// Print what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Print the value of a single key
+    Get {
+        /// Directory path like /app/foo
+        path: String,
+        /// Key relative to the directory: raw bytes (supports \xHH escapes) or a tuple literal
+        key: String,
+        #[command(flatten)]
+        read: ReadArgs,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Print a shell completion script for fdbdir's commands and flags
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+    /// Block until a key changes, then print its new value
+    Watch {
+        /// Directory path like /app/foo
+        path: String,
+        /// Key relative to the directory: raw bytes (supports \xHH escapes) or a tuple literal
+        key: String,
+    },
+    /// Print a timestamped line whenever any key in a directory changes, until Ctrl-C
+    WatchDir {
+        /// Directory path like /app/foo
+        path: String,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ReadArgs {
+    /// Use snapshot reads (default). No read conflict ranges are added, so
+    /// scans don't contend with writers; reads won't observe writes made
+    /// earlier in the same transaction, which the explorer never does anyway
+    #[arg(long, overrides_with = "no_snapshot")]
+    snapshot: bool,
+    /// Use serializable reads instead of snapshot reads
+    #[arg(long)]
+    no_snapshot: bool,
+    /// Read at a fixed version (must still be within the ~5s MVCC window)
+    #[arg(long, value_name = "V")]
+    at_version: Option<i64>,
+}
+
+impl ReadArgs {
+    fn options(&self) -> util::ReadOptions {
+        util::ReadOptions {
+            snapshot: !self.no_snapshot,
+            read_version: self.at_version,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct FormatArgs {
+    /// Break nested tuples across indented lines
+    #[arg(long)]
+    pretty: bool,
+    /// Render values as an offset+hex+ASCII dump
+    #[arg(long)]
+    hex: bool,
+    /// Render keys as an offset+hex+ASCII dump
+    #[arg(long)]
+    hex_keys: bool,
+    /// Render tuple integers in hexadecimal (e.g. 0x1f, -0x10)
+    #[arg(long)]
+    int_hex: bool,
+    /// Render undecodable keys and values as base64 (b64:...) instead of \xHH escapes
+    #[arg(long)]
+    b64: bool,
+    /// Render versionstamps as raw hex instead of version/batch/user fields
+    #[arg(long)]
+    vs_hex: bool,
+    /// Print floats and doubles with exactly N decimal places
+    #[arg(long, value_name = "N")]
+    precision: Option<usize>,
+    /// Omit the f32/f64 suffix on floats and doubles
+    #[arg(long)]
+    bare_floats: bool,
+    /// Truncate byte strings and hex dumps after N bytes (0 = no limit)
+    #[arg(long, value_name = "N", default_value_t = format::DEFAULT_MAX_BYTES)]
+    max_bytes: usize,
+    /// Truncate keys after N bytes instead of --max-bytes
+    #[arg(long, value_name = "N")]
+    max_key_bytes: Option<usize>,
+    /// Truncate values after N bytes instead of --max-bytes
+    #[arg(long, value_name = "N")]
+    max_value_bytes: Option<usize>,
+    /// Print all values in one color instead of coloring tuple elements by type
+    #[arg(long)]
+    flat_colors: bool,
+    /// Show full database keys (directory prefix included) as escaped bytes
+    #[arg(long)]
+    absolute_keys: bool,
+    /// Decode byte strings inside tuples that are themselves packed tuples
+    #[arg(long)]
+    deep_decode: bool,
+    /// Read values as this type; values that don't fit it print as usual
+    #[arg(long, value_enum, value_name = "TYPE")]
+    value_as: Option<format::ValueAs>,
+    /// Print whole rows instead of cutting them to the terminal width
+    #[arg(long)]
+    full: bool,
+}
+
+impl FormatArgs {
+    // `fit` cuts rows to the terminal; off when output goes to a file (-o).
+    fn options(&self, fit: bool) -> format::FormatOptions {
+        format::FormatOptions {
+            pretty: self.pretty,
+            hex_keys: self.hex_keys,
+            hex_values: self.hex,
+            int_hex: self.int_hex,
+            raw_keys: false,
+            b64: self.b64,
+            vs_hex: self.vs_hex,
+            precision: self.precision,
+            bare_floats: self.bare_floats,
+            max_key_bytes: self.max_key_bytes.unwrap_or(self.max_bytes),
+            max_value_bytes: self.max_value_bytes.unwrap_or(self.max_bytes),
+            flat_colors: self.flat_colors,
+            absolute_keys: self.absolute_keys,
+            deep_decode: self.deep_decode,
+            value_as: self.value_as,
+            width: if self.full || !fit {
+                None
+            } else {
+                util::terminal_width()
+            },
+        }
+    }
+}
+
+// The client reads connection strings only from a file, so --cluster-string
+// is written to a temporary one that is removed when this is dropped.
+struct TempClusterFile(std::path::PathBuf);
+
+impl TempClusterFile {
+    fn write(connection: &str) -> Result<Self> {
+        // create_new refuses a file or symlink someone else planted at the
+        // name, so a clash just moves on to a fresh one; only we can read it.
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        for attempt in 0..16 {
+            let name = format!("fdbdir-{}-{nanos:x}-{attempt}.cluster", std::process::id());
+            let path = std::env::temp_dir().join(name);
+            let mut opts = std::fs::OpenOptions::new();
+            opts.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+            match opts.open(&path) {
+                Ok(mut file) => {
+                    let temp = Self(path);
+                    writeln!(file, "{connection}")
+                        .with_context(|| format!("cannot write {}", temp.0.display()))?;
+                    return Ok(temp);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("cannot create {}", path.display()))
+                }
+            }
+        }
+        Err(anyhow::anyhow!(
+            "cannot create a temporary cluster file in {}",
+            std::env::temp_dir().display()
+        ))
+    }
+}
+
+impl Drop for TempClusterFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Failures while starting the client, which FoundationDB reports only by code,
+// with what to check for the common ones.
+fn startup_error(
+    e: foundationdb::FdbError,
+    api_version: i32,
+    cluster_file: Option<&str>,
+) -> anyhow::Error {
+    let cluster_file = cluster_file.unwrap_or("(default)");
+    let hint = match e.code() {
+        // api_version_invalid / api_version_not_supported
+        2202 | 2203 => format!(
+            "API version {api_version} is not usable: the installed libfdb_c supports up to {}, \
+             and this build up to {}; pass a supported --api-version or install a newer client",
+            foundationdb::api::get_max_api_version(),
+            foundationdb::api::FdbApiBuilder::default().runtime_version()
+        ),
+        // no_cluster_file_found
+        1515 => "no cluster file found: pass --cluster-file (or a --profile with cluster_file), \
+                 set FDB_CLUSTER_FILE, or install one at the default location \
+                 (/etc/foundationdb/fdb.cluster on Linux, /usr/local/etc/foundationdb/fdb.cluster on macOS)"
+            .to_string(),
+        // file_not_found / file_not_readable
+        1511 | 1513 => format!("cannot read cluster file {cluster_file}"),
+        // connection_string_invalid
+        2104 => format!(
+            "cluster file {cluster_file} does not hold a valid connection string \
+             (description:id@host:port[,host:port...])"
+        ),
+        // tls_error
+        2107 => "TLS setup failed: check that --tls-cert-file, --tls-key-file, and --tls-ca-file \
+                 name readable PEM files that belong together"
+            .to_string(),
+        _ => {
+            return anyhow::anyhow!(
+                "cannot start the FoundationDB client: {}",
+                util::describe_error(e)
+            )
+        }
+    };
+    anyhow::anyhow!("{hint} ({})", util::describe_error(e))
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // Static CLI surface only; needs no connection.
+    if let Some(Commands::Completions { shell }) = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "fdbdir", &mut std::io::stdout());
+        return Ok(());
+    }
+
     // Default to interactive mode when no subcommand/flags are provided.
     // This matches typical CLI REPL expectations and avoids panicking on None.
-    let default_repl = !cli.interactive && cli.command.is_none();
+    let batch = cli.script || cli.exec_file.is_some();
+    let default_repl = !cli.interactive && !batch && cli.command.is_none();
+
+    let parse = |s: &Option<String>| s.as_deref().map(parse::parse_bytes_literal).transpose();
+    let mut client_options = util::ClientOptions {
+        node_prefix: parse(&cli.node_prefix)?,
+        content_prefix: parse(&cli.content_prefix)?,
+        tag: cli.tag,
+        transaction_options: cli.tr_options,
+        low_read_priority: cli.low_read_priority,
+        verbose_retries: cli.verbose_retries,
+        api_version: None,
+    };
+    if cli.batch_priority {
+        client_options
+            .transaction_options
+            .push(TransactionOption::PriorityBatch);
+    }
 
     // Only connect if needed
-    let need_db = cli.interactive || default_repl || cli.command.is_some();
+    let need_db = cli.interactive || batch || default_repl || cli.command.is_some();
+    let timing = cli.timing;
+    // Explicit flags win over the selected profile.
+    let profile = match cli.profile.as_deref() {
+        Some(name) => config::profile(&config::load()?, name)?,
+        None => config::Profile::default(),
+    };
+    let mut temp_cluster = None;
     let (network, db) = if need_db && !cli.no_connect {
+        let started = std::time::Instant::now();
+        let mut api = foundationdb::api::FdbApiBuilder::default();
+        if let Some(v) = cli.api_version.or(profile.api_version) {
+            api = api.set_runtime_version(v);
+        }
+        let api_version = api.runtime_version();
+        client_options.api_version = Some(api_version);
+        client_options.check()?;
+        if let Some(connection) = cli.cluster_string.as_deref() {
+            temp_cluster = Some(TempClusterFile::write(connection)?);
+        }
+        let cluster_file = cli
+            .cluster_file
+            .or(temp_cluster
+                .as_ref()
+                .map(|f| f.0.to_string_lossy().into_owned()))
+            .or(profile.cluster_file);
+        let fail = |e| startup_error(e, api_version, cluster_file.as_deref());
+        let mut network = api.build().map_err(fail)?;
+        let tls = [
+            cli.tls_cert_file
+                .or(profile.tls_cert_file)
+                .map(NetworkOption::TLSCertPath),
+            cli.tls_key_file
+                .or(profile.tls_key_file)
+                .map(NetworkOption::TLSKeyPath),
+            cli.tls_ca_file
+                .or(profile.tls_ca_file)
+                .map(NetworkOption::TLSCaPath),
+        ];
+        for option in tls.into_iter().flatten() {
+            network = network.set_option(option).map_err(fail)?;
+        }
         // Safety: we drop the handle at program end
-        let network = unsafe { foundationdb::boot() };
-        let db = match cli.cluster_file {
-            Some(path) => foundationdb::Database::from_path(&path)?,
-            None => foundationdb::Database::default()?,
-        };
-        (Some(network), Some(db))
+        let network = unsafe { network.boot().map_err(fail)? };
+        let db = match cluster_file.as_deref() {
+            Some(path) => foundationdb::Database::from_path(path),
+            None => foundationdb::Database::default(),
+        }
+        .map_err(fail)?;
+        for option in cli.db_options {
+            let name = format!("{option:?}");
+            db.set_option(option)
+                .map_err(|e| anyhow::anyhow!(util::describe_error(e)))
+                .with_context(|| format!("cannot set database option {name}"))?;
+        }
+        if timing {
+            format::print_timing("connect", started.elapsed(), &[]);
+        }
+        (Some(network), Some(util::Client::new(db, client_options)))
     } else {
         (None, None)
     };
@@ -73,40 +745,595 @@ async fn main() -> Result<()> {
         let db = db.ok_or_else(|| {
             anyhow::anyhow!("interactive mode requires a connection; omit --no-connect")
         })?;
-        repl::run_repl(db).await?;
+        let history = (!cli.no_history).then(|| repl::history_path(cli.history_file.clone()));
+        repl::run_repl(db, history, timing).await?;
         drop(network);
         return Ok(());
     }
 
+    let mut out = match cli.output.as_deref() {
+        Some(file) => output::Output::file(file).with_context(|| format!("cannot write {file}"))?,
+        None if cli.pager && !batch && std::io::stdout().is_terminal() => {
+            let stdout = output::Output::stdout();
+            output::Output::pager(stdout.color()).unwrap_or(stdout)
+        }
+        None => output::Output::stdout(),
+    };
+    // Rows written to a file (-o) are never cut to the terminal width.
+    let fit = cli.output.is_none();
+    // Progress bars go to stderr, so only when the rows are going elsewhere.
+    let progress_bar = std::io::stderr().is_terminal()
+        && (cli.output.is_some() || !std::io::stdout().is_terminal());
+
+    if cli.script {
+        let db = db.ok_or_else(|| {
+            anyhow::anyhow!("script mode requires a connection; omit --no-connect")
+        })?;
+        let res = repl::run_script(
+            db,
+            std::io::stdin().lock(),
+            out.no_color(),
+            cli.keep_going,
+            timing,
+        )
+        .await;
+        drop(network);
+        return res;
+    }
+
+    if let Some(path) = cli.exec_file.as_deref() {
+        let db = db.ok_or_else(|| {
+            anyhow::anyhow!("--exec-file requires a connection; omit --no-connect")
+        })?;
+        let input = std::io::BufReader::new(
+            std::fs::File::open(path).with_context(|| format!("cannot read {path}"))?,
+        );
+        let res = repl::run_script(db, input, out.no_color(), cli.keep_going, timing).await;
+        drop(network);
+        return res;
+    }
+
+    let started = std::time::Instant::now();
+    // Non-zero without an error message, for checks like `exists`.
+    let mut exit_code = 0;
     match cli.command.unwrap() {
-        Commands::Ls { path } => {
+        Commands::Ls {
+            path,
+            counts,
+            sort,
+            by_prefix,
+            long,
+            keys,
+            output_format,
+            layer,
+            parallel,
+            fail_on_empty,
+            read,
+            format,
+        } => {
             let db =
                 db.ok_or_else(|| anyhow::anyhow!("ls requires a connection; omit --no-connect"))?;
-            util::ls_path(&db, util::parse_path(path.as_deref().unwrap_or("/"))).await?;
+            let listings = util::ls_path(
+                util::Source::Db(&db),
+                &mut out,
+                parse::parse_path_pattern(path.as_deref().unwrap_or("/")),
+                util::LsOptions {
+                    counts,
+                    sort: sort.or(by_prefix.then_some(util::LsSort::Prefix)),
+                    long,
+                    format: output_format,
+                    keys,
+                    layer: layer
+                        .as_deref()
+                        .map(parse::parse_bytes_literal)
+                        .transpose()?,
+                    parallel: parallel.into(),
+                },
+                read.options(),
+                format.options(fit),
+            )
+            .await?;
+            if timing {
+                for listing in &listings {
+                    format::print_read_timing("ls", listing.timing);
+                }
+            }
+            if fail_on_empty && listings.iter().map(util::Listing::count).sum::<usize>() == 0 {
+                exit_code = 1;
+            }
         }
         Commands::Scan {
             path,
             limit,
+            all,
+            tail,
             prefix,
+            prefix_tuple,
+            after,
+            begin_sel,
+            end_sel,
             raw,
+            keys_only,
+            fields,
+            schema,
+            verify_roundtrip,
+            max_scan_bytes,
+            output_format,
+            layer,
+            fail_on_empty,
+            read,
+            format,
         } => {
             let db =
                 db.ok_or_else(|| anyhow::anyhow!("scan requires a connection; omit --no-connect"))?;
-            let prefix_bytes = if let Some(s) = prefix {
-                Some(util::parse_bytes_literal(&s)?)
-            } else {
-                None
+            let scan = util::ScanOptions {
+                limit: (!all).then_some(limit),
+                prefix: match prefix.as_deref() {
+                    Some(p) if prefix_tuple => Some(parse::parse_prefix_element(p)?),
+                    p => p.map(parse::parse_key_literal).transpose()?,
+                },
+                after: after.as_deref().map(parse::parse_key_literal).transpose()?,
+                subspace: false,
+                keys_only,
+                streaming: None,
+                fields: fields.as_deref().map(parse::parse_fields).transpose()?,
+                schema: schema.as_deref().map(parse::parse_schema).transpose()?,
+                verify_roundtrip,
+                max_scan_bytes,
+                begin_sel: begin_sel
+                    .as_deref()
+                    .map(parse::parse_selector)
+                    .transpose()?,
+                end_sel: end_sel.as_deref().map(parse::parse_selector).transpose()?,
+                progress_bar,
+                tail: tail.map(|n| n as usize),
+                format: output_format,
+                layer: layer
+                    .as_deref()
+                    .map(parse::parse_bytes_literal)
+                    .transpose()?,
             };
-            util::scan_path(
+            let mut fmt = format.options(fit);
+            fmt.raw_keys = raw;
+            let scanned = util::scan_path(
+                util::Source::Db(&db),
+                &mut out,
+                parse::parse_path(path.as_deref().unwrap_or("/")),
+                scan,
+                read.options(),
+                fmt,
+            )
+            .await?;
+            format::print_scan_summary(&scanned);
+            if timing {
+                format::print_read_timing("scan", scanned.timing);
+            }
+            if fail_on_empty && scanned.rows == 0 {
+                exit_code = 1;
+            }
+        }
+        Commands::Rawscan {
+            begin,
+            end,
+            limit,
+            all,
+            system,
+            read,
+            format,
+        } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("rawscan requires a connection; omit --no-connect")
+            })?;
+            let raw = util::RawScanOptions {
+                begin: parse::parse_key_literal(&begin)?,
+                end: end.as_deref().map(parse::parse_key_literal).transpose()?,
+                limit: (!all).then_some(limit),
+                system,
+                streaming: None,
+            };
+            util::raw_scan(&db, &mut out, raw, read.options(), format.options(fit)).await?;
+        }
+        Commands::Version => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("version requires a connection; omit --no-connect")
+            })?;
+            writeln!(out, "{}", util::read_version(&db).await?)?;
+        }
+        Commands::Env => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("env requires a connection; omit --no-connect"))?;
+            status::print_env(&db, &mut out).await?;
+        }
+        Commands::ClusterStatus { raw } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("cluster-status requires a connection; omit --no-connect")
+            })?;
+            status::print_cluster_status(&db, &mut out, raw).await?;
+        }
+        Commands::Workers => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("workers requires a connection; omit --no-connect")
+            })?;
+            status::print_workers(&db, &mut out).await?;
+        }
+        Commands::Export {
+            path,
+            parallel,
+            max_scan_bytes,
+        } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("export requires a connection; omit --no-connect")
+            })?;
+            let n = dump::export_path(
                 &db,
-                util::parse_path(path.as_deref().unwrap_or("/")),
-                limit,
-                prefix_bytes,
-                raw,
+                parse::parse_path(&path),
+                &mut out,
+                parallel.into(),
+                max_scan_bytes,
+                progress_bar,
+            )
+            .await?;
+            if let Some(file) = cli.output.as_deref() {
+                println!("exported {n} keys to {file}");
+            }
+        }
+        Commands::ExportTree { path, depth } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("export-tree requires a connection; omit --no-connect")
+            })?;
+            let path = parse::parse_path(path.as_deref().unwrap_or("/"));
+            let n = dump::export_tree(&db, &mut out, path, depth).await?;
+            if let Some(file) = cli.output.as_deref() {
+                println!("exported {n} directories to {file}");
+            }
+        }
+        Commands::Import {
+            file,
+            path,
+            skip_existing,
+            dry_run,
+            yes,
+        } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("import requires a connection; omit --no-connect")
+            })?;
+            let mode = if skip_existing {
+                dump::ImportMode::SkipExisting
+            } else {
+                dump::ImportMode::Overwrite
+            };
+            let path = parse::parse_path(&path);
+            if !dry_run && mode == dump::ImportMode::Overwrite {
+                // With the dump on stdin there is no terminal left to answer on.
+                let interactive = file != "-" && std::io::stdin().is_terminal();
+                let prompt = format!(
+                    "Import {file} into {}, overwriting existing keys?",
+                    format::display_path(&path)
+                );
+                if !util::approve("import", &prompt, yes, interactive)? {
+                    return Err(anyhow::anyhow!("aborted"));
+                }
+            }
+            if file == "-" {
+                let input = std::io::stdin().lock();
+                dump::import_path(&db, &mut out, path, input, mode, dry_run).await?;
+            } else {
+                let input = std::io::BufReader::new(std::fs::File::open(&file)?);
+                dump::import_path(&db, &mut out, path, input, mode, dry_run).await?;
+            }
+        }
+        Commands::Load {
+            file,
+            path,
+            clear_first,
+            dry_run,
+            yes,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("load requires a connection; omit --no-connect"))?;
+            let path = parse::parse_path(&path);
+            if !dry_run {
+                let interactive = file != "-" && std::io::stdin().is_terminal();
+                let prompt = if clear_first {
+                    format!(
+                        "Clear {} and load {file} into it?",
+                        format::display_path(&path)
+                    )
+                } else {
+                    format!(
+                        "Load {file} into {}, overwriting existing keys?",
+                        format::display_path(&path)
+                    )
+                };
+                if !util::approve("load", &prompt, yes, interactive)? {
+                    return Err(anyhow::anyhow!("aborted"));
+                }
+            }
+            if file == "-" {
+                let input = std::io::stdin().lock();
+                dump::load_path(&db, &mut out, path, input, clear_first, dry_run).await?;
+            } else {
+                let input = std::io::BufReader::new(std::fs::File::open(&file)?);
+                dump::load_path(&db, &mut out, path, input, clear_first, dry_run).await?;
+            }
+        }
+        Commands::Cp {
+            src,
+            dst,
+            layer,
+            dry_run,
+            yes,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("cp requires a connection; omit --no-connect"))?;
+            let layer = match layer {
+                Some(s) => Some(parse::parse_bytes_literal(&s)?),
+                None => None,
+            };
+            let (src, dst) = (parse::parse_path(&src), parse::parse_path(&dst));
+            let prompt = format!(
+                "Copy {} into {}, overwriting keys that exist there?",
+                format::display_path(&src),
+                format::display_path(&dst)
+            );
+            if !dry_run && !util::approve("cp", &prompt, yes, std::io::stdin().is_terminal())? {
+                return Err(anyhow::anyhow!("aborted"));
+            }
+            dump::copy_path(&db, &mut out, src, dst, layer, dry_run).await?;
+        }
+        Commands::Diff {
+            a,
+            b,
+            verbose,
+            format,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("diff requires a connection; omit --no-connect"))?;
+            let (a, b) = (parse::parse_path(&a), parse::parse_path(&b));
+            let counts =
+                dump::diff_paths(&db, &mut out, a, b, verbose, format.options(fit)).await?;
+            if counts.removed + counts.added + counts.changed > 0 {
+                exit_code = 1;
+            }
+        }
+        Commands::Find {
+            pattern,
+            path,
+            max_depth,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("find requires a connection; omit --no-connect"))?;
+            let pattern = glob::Pattern::new(&pattern)?;
+            let path = parse::parse_path(path.as_deref().unwrap_or("/"));
+            util::find_dirs(&db, &mut out, path, &pattern, max_depth).await?;
+        }
+        Commands::Grep {
+            pattern,
+            path,
+            limit,
+            all,
+            ignore_case,
+            format,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("grep requires a connection; omit --no-connect"))?;
+            let re = regex::RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()?;
+            let path = parse::parse_path(path.as_deref().unwrap_or("/"));
+            let limit = (!all).then_some(limit);
+            dump::grep_path(&db, &mut out, path, &re, limit, format.options(fit)).await?;
+        }
+        Commands::Groupby {
+            path,
+            limit,
+            all,
+            format,
+        } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("groupby requires a connection; omit --no-connect")
+            })?;
+            let path = parse::parse_path(path.as_deref().unwrap_or("/"));
+            let limit = (!all).then_some(limit);
+            dump::group_path(&db, &mut out, path, limit, format.options(fit)).await?;
+        }
+        Commands::Exists { path, quiet } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("exists requires a connection; omit --no-connect")
+            })?;
+            let exists = util::dir_exists(&db, &parse::parse_path(&path)).await?;
+            if !quiet {
+                writeln!(out, "{exists}")?;
+            }
+            if !exists {
+                exit_code = 1;
+            }
+        }
+        Commands::FindPrefix { bytes } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("find-prefix requires a connection; omit --no-connect")
+            })?;
+            util::find_prefix(&db, &mut out, &parse::parse_key_literal(&bytes)?).await?;
+        }
+        Commands::Mkdir { path, layer } => {
+            let db = db
+                .ok_or_else(|| anyhow::anyhow!("mkdir requires a connection; omit --no-connect"))?;
+            let layer = layer
+                .as_deref()
+                .map(parse::parse_bytes_literal)
+                .transpose()?;
+            util::make_dir(&db, &parse::parse_path(&path), layer.as_deref()).await?;
+        }
+        Commands::Rm { path, dry_run, yes } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("rm requires a connection; omit --no-connect"))?;
+            let path = parse::parse_path(&path);
+            if dry_run {
+                util::plan_remove(&db, &mut out, &path).await?;
+            } else {
+                let prompt = format!(
+                    "Remove {} and everything under it?",
+                    format::display_path(&path)
+                );
+                if !util::approve("rm", &prompt, yes, std::io::stdin().is_terminal())? {
+                    return Err(anyhow::anyhow!("aborted"));
+                }
+                util::remove_dir(&db, &path).await?;
+            }
+        }
+        Commands::ClearPrefix {
+            path,
+            prefix,
+            dry_run,
+            yes,
+        } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("clear-prefix requires a connection; omit --no-connect")
+            })?;
+            let path = parse::parse_path(&path);
+            let prefix = parse::parse_key_literal(&prefix)?;
+            if dry_run {
+                util::plan_clear_prefix(&db, &mut out, &path, &prefix).await?;
+            } else {
+                let prompt = format!(
+                    "Clear every key under {} in {}?",
+                    format::key_literal(&prefix),
+                    format::display_path(&path)
+                );
+                if !util::approve("clear-prefix", &prompt, yes, std::io::stdin().is_terminal())? {
+                    return Err(anyhow::anyhow!("aborted"));
+                }
+                util::clear_prefix(&db, &path, &prefix).await?;
+            }
+        }
+        Commands::Mv {
+            src,
+            dst,
+            dry_run,
+            yes,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("mv requires a connection; omit --no-connect"))?;
+            let (src, dst) = (parse::parse_path(&src), parse::parse_path(&dst));
+            if dry_run {
+                util::plan_move(&db, &mut out, &src, &dst).await?;
+            } else {
+                let prompt = format!(
+                    "Move {} to {}?",
+                    format::display_path(&src),
+                    format::display_path(&dst)
+                );
+                if !util::approve("mv", &prompt, yes, std::io::stdin().is_terminal())? {
+                    return Err(anyhow::anyhow!("aborted"));
+                }
+                util::move_dir(&db, &src, &dst).await?;
+            }
+        }
+        Commands::Get {
+            path,
+            key,
+            read,
+            format,
+        } => {
+            let db =
+                db.ok_or_else(|| anyhow::anyhow!("get requires a connection; omit --no-connect"))?;
+            let key = parse::parse_key_literal(&key)?;
+            util::get_value(
+                util::Source::Db(&db),
+                &mut out,
+                parse::parse_path(&path),
+                key,
+                read.options(),
+                format.options(fit),
             )
             .await?;
         }
+        Commands::Completions { .. } => unreachable!("handled before connecting"),
+        Commands::Watch { path, key } => {
+            let db = db
+                .ok_or_else(|| anyhow::anyhow!("watch requires a connection; omit --no-connect"))?;
+            let key = parse::parse_key_literal(&key)?;
+            util::watch_key(&db, &mut out, parse::parse_path(&path), key).await?;
+        }
+        Commands::WatchDir { path } => {
+            let db = db.ok_or_else(|| {
+                anyhow::anyhow!("watch-dir requires a connection; omit --no-connect")
+            })?;
+            util::watch_dir(&db, &mut out, parse::parse_path(&path)).await?;
+        }
+    }
+    out.finish()?;
+    if timing {
+        format::print_timing("total", started.elapsed(), &[]);
     }
     drop(network);
+    // process::exit skips destructors.
+    drop(temp_cluster);
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_cluster_file_is_private_and_removed() {
+        let first = TempClusterFile::write("desc:id@127.0.0.1:4500").unwrap();
+        let second = TempClusterFile::write("other:id@127.0.0.1:4500").unwrap();
+        assert_ne!(first.0, second.0);
+        let text = std::fs::read_to_string(&first.0).unwrap();
+        assert_eq!(text, "desc:id@127.0.0.1:4500\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first.0).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let path = first.0.clone();
+        drop(first);
+        assert!(!path.exists());
+    }
+
+    // Only the client is started; nothing here talks to a cluster.
+    fn offline_client(cluster: &TempClusterFile) -> util::Client {
+        static BOOT: std::sync::Once = std::sync::Once::new();
+        BOOT.call_once(|| std::mem::forget(unsafe { foundationdb::boot() }));
+        let db = foundationdb::Database::from_path(&cluster.0.to_string_lossy())
+            .expect("open the cluster file");
+        util::Client::new(db, util::ClientOptions::default())
+    }
+
+    #[tokio::test]
+    async fn exec_file_runs_each_line() {
+        let cluster = TempClusterFile::write("desc:id@127.0.0.1:1").unwrap();
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let script = dir.join(format!("fdbdir-exec-{id}.txt"));
+        let result = dir.join(format!("fdbdir-exec-{id}.out"));
+        std::fs::write(&script, "# comment\n\npwd\nset limit 7\n\npwd\n").unwrap();
+
+        let input = std::io::BufReader::new(std::fs::File::open(&script).unwrap());
+        let out = output::Output::file(&result.to_string_lossy()).unwrap();
+        repl::run_script(offline_client(&cluster), input, out, false, false)
+            .await
+            .unwrap();
+        let printed = std::fs::read_to_string(&result).unwrap();
+
+        std::fs::write(&script, "pwd\nbogus\npwd\n").unwrap();
+        let input = std::io::BufReader::new(std::fs::File::open(&script).unwrap());
+        let out = output::Output::file(&result.to_string_lossy()).unwrap();
+        let err = repl::run_script(offline_client(&cluster), input, out, false, false)
+            .await
+            .unwrap_err();
+        let stopped = std::fs::read_to_string(&result).unwrap();
+        std::fs::remove_file(&script).unwrap();
+        std::fs::remove_file(&result).unwrap();
+
+        assert_eq!(printed, "/\n/\n");
+        assert!(format!("{err:#}").starts_with("line 2: bogus"), "{err:#}");
+        assert_eq!(stopped, "/\n");
+    }
+}
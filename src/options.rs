@@ -0,0 +1,250 @@
+use anyhow::{anyhow, Result};
+use foundationdb::options::{DatabaseOption, TransactionOption};
+
+// One `name[=value]` option spec. Names are FoundationDB's own
+// (location_cache_size); '-' is accepted in place of '_'.
+struct Spec<'a> {
+    name: String,
+    value: Option<&'a str>,
+}
+
+impl<'a> Spec<'a> {
+    fn parse(s: &'a str) -> Self {
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (s, None),
+        };
+        Self {
+            name: name.trim().to_ascii_lowercase().replace('-', "_"),
+            value,
+        }
+    }
+
+    fn string(&self) -> Result<String> {
+        self.value
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("option '{0}' needs a value: {0}=<value>", self.name))
+    }
+
+    fn int(&self) -> Result<i32> {
+        let value = self.string()?;
+        value
+            .parse()
+            .map_err(|_| anyhow!("option '{}' needs an integer, got '{value}'", self.name))
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>> {
+        crate::parse::parse_bytes_literal(&self.string()?)
+    }
+
+    // Options that take no value.
+    fn flag<T>(&self, option: T) -> Result<T> {
+        match self.value {
+            None => Ok(option),
+            Some(_) => Err(anyhow!("option '{}' takes no value", self.name)),
+        }
+    }
+}
+
+fn unknown(kind: &str, name: &str, known: &[&str]) -> anyhow::Error {
+    anyhow!(
+        "unknown {kind} option '{name}'; supported: {}",
+        known.join(", ")
+    )
+}
+
+// Every DatabaseOption, with the value each one takes.
+const DATABASE_OPTIONS: &[&str] = &[
+    "location_cache_size=<int>",
+    "max_watches=<int>",
+    "machine_id=<hex>",
+    "datacenter_id=<hex>",
+    "snapshot_ryw_enable",
+    "snapshot_ryw_disable",
+    "transaction_logging_max_field_length=<int>",
+    "transaction_timeout=<ms>",
+    "transaction_retry_limit=<int>",
+    "transaction_max_retry_delay=<ms>",
+    "transaction_size_limit=<bytes>",
+    "transaction_causal_read_risky",
+    "transaction_include_port_in_address",
+    "transaction_bypass_unreadable",
+    "use_config_database",
+    "test_causal_read_risky",
+];
+
+// --db-option: applied to the Database right after it is opened.
+pub fn parse_database_option(s: &str) -> Result<DatabaseOption> {
+    let spec = Spec::parse(s);
+    Ok(match spec.name.as_str() {
+        "location_cache_size" => DatabaseOption::LocationCacheSize(spec.int()?),
+        "max_watches" => DatabaseOption::MaxWatches(spec.int()?),
+        "machine_id" => DatabaseOption::MachineId(spec.string()?),
+        "datacenter_id" => DatabaseOption::DatacenterId(spec.string()?),
+        "snapshot_ryw_enable" => spec.flag(DatabaseOption::SnapshotRywEnable)?,
+        "snapshot_ryw_disable" => spec.flag(DatabaseOption::SnapshotRywDisable)?,
+        "transaction_logging_max_field_length" => {
+            DatabaseOption::TransactionLoggingMaxFieldLength(spec.int()?)
+        }
+        "transaction_timeout" => DatabaseOption::TransactionTimeout(spec.int()?),
+        "transaction_retry_limit" => DatabaseOption::TransactionRetryLimit(spec.int()?),
+        "transaction_max_retry_delay" => DatabaseOption::TransactionMaxRetryDelay(spec.int()?),
+        "transaction_size_limit" => DatabaseOption::TransactionSizeLimit(spec.int()?),
+        "transaction_causal_read_risky" => spec.flag(DatabaseOption::TransactionCausalReadRisky)?,
+        "transaction_include_port_in_address" => {
+            spec.flag(DatabaseOption::TransactionIncludePortInAddress)?
+        }
+        "transaction_bypass_unreadable" => {
+            spec.flag(DatabaseOption::TransactionBypassUnreadable)?
+        }
+        "use_config_database" => spec.flag(DatabaseOption::UseConfigDatabase)?,
+        "test_causal_read_risky" => spec.flag(DatabaseOption::TestCausalReadRisky)?,
+        name => return Err(unknown("database", name, DATABASE_OPTIONS)),
+    })
+}
+
+// Every TransactionOption, with the value each one takes.
+const TRANSACTION_OPTIONS: &[&str] = &[
+    "causal_write_risky",
+    "causal_read_risky",
+    "causal_read_disable",
+    "include_port_in_address",
+    "next_write_no_write_conflict_range",
+    "read_your_writes_disable",
+    "read_ahead_disable",
+    "durability_datacenter",
+    "durability_risky",
+    "durability_dev_null_is_web_scale",
+    "priority_system_immediate",
+    "priority_batch",
+    "initialize_new_database",
+    "access_system_keys",
+    "read_system_keys",
+    "raw_access",
+    "debug_retry_logging=<name>",
+    "transaction_logging_enable=<id>",
+    "debug_transaction_identifier=<id>",
+    "log_transaction",
+    "transaction_logging_max_field_length=<int>",
+    "server_request_tracing",
+    "timeout=<ms>",
+    "retry_limit=<int>",
+    "max_retry_delay=<ms>",
+    "size_limit=<bytes>",
+    "snapshot_ryw_enable",
+    "snapshot_ryw_disable",
+    "lock_aware",
+    "used_during_commit_protection_disable",
+    "read_lock_aware",
+    "use_provisional_proxies",
+    "report_conflicting_keys",
+    "special_key_space_relaxed",
+    "special_key_space_enable_writes",
+    "tag=<tag>",
+    "auto_throttle_tag=<tag>",
+    "span_parent=<bytes>",
+    "expensive_clear_cost_estimation_enable",
+    "bypass_unreadable",
+    "use_grv_cache",
+];
+
+// --tr-option: set on each transaction ls/scan/get/rawscan (and `begin`) read in.
+pub fn parse_transaction_option(s: &str) -> Result<TransactionOption> {
+    use TransactionOption as T;
+    let spec = Spec::parse(s);
+    Ok(match spec.name.as_str() {
+        "causal_write_risky" => spec.flag(T::CausalWriteRisky)?,
+        "causal_read_risky" => spec.flag(T::CausalReadRisky)?,
+        "causal_read_disable" => spec.flag(T::CausalReadDisable)?,
+        "include_port_in_address" => spec.flag(T::IncludePortInAddress)?,
+        "next_write_no_write_conflict_range" => spec.flag(T::NextWriteNoWriteConflictRange)?,
+        "read_your_writes_disable" => spec.flag(T::ReadYourWritesDisable)?,
+        "read_ahead_disable" => spec.flag(T::ReadAheadDisable)?,
+        "durability_datacenter" => spec.flag(T::DurabilityDatacenter)?,
+        "durability_risky" => spec.flag(T::DurabilityRisky)?,
+        "durability_dev_null_is_web_scale" => spec.flag(T::DurabilityDevNullIsWebScale)?,
+        "priority_system_immediate" => spec.flag(T::PrioritySystemImmediate)?,
+        "priority_batch" => spec.flag(T::PriorityBatch)?,
+        "initialize_new_database" => spec.flag(T::InitializeNewDatabase)?,
+        "access_system_keys" => spec.flag(T::AccessSystemKeys)?,
+        "read_system_keys" => spec.flag(T::ReadSystemKeys)?,
+        "raw_access" => spec.flag(T::RawAccess)?,
+        "debug_retry_logging" => T::DebugRetryLogging(spec.string()?),
+        "transaction_logging_enable" => T::TransactionLoggingEnable(spec.string()?),
+        "debug_transaction_identifier" => T::DebugTransactionIdentifier(spec.string()?),
+        "log_transaction" => spec.flag(T::LogTransaction)?,
+        "transaction_logging_max_field_length" => T::TransactionLoggingMaxFieldLength(spec.int()?),
+        "server_request_tracing" => spec.flag(T::ServerRequestTracing)?,
+        "timeout" => T::Timeout(spec.int()?),
+        "retry_limit" => T::RetryLimit(spec.int()?),
+        "max_retry_delay" => T::MaxRetryDelay(spec.int()?),
+        "size_limit" => T::SizeLimit(spec.int()?),
+        "snapshot_ryw_enable" => spec.flag(T::SnapshotRywEnable)?,
+        "snapshot_ryw_disable" => spec.flag(T::SnapshotRywDisable)?,
+        "lock_aware" => spec.flag(T::LockAware)?,
+        "used_during_commit_protection_disable" => {
+            spec.flag(T::UsedDuringCommitProtectionDisable)?
+        }
+        "read_lock_aware" => spec.flag(T::ReadLockAware)?,
+        "use_provisional_proxies" => spec.flag(T::UseProvisionalProxies)?,
+        "report_conflicting_keys" => spec.flag(T::ReportConflictingKeys)?,
+        "special_key_space_relaxed" => spec.flag(T::SpecialKeySpaceRelaxed)?,
+        "special_key_space_enable_writes" => spec.flag(T::SpecialKeySpaceEnableWrites)?,
+        "tag" => T::Tag(spec.string()?),
+        "auto_throttle_tag" => T::AutoThrottleTag(spec.string()?),
+        "span_parent" => T::SpanParent(spec.bytes()?),
+        "expensive_clear_cost_estimation_enable" => {
+            spec.flag(T::ExpensiveClearCostEstimationEnable)?
+        }
+        "bypass_unreadable" => spec.flag(T::BypassUnreadable)?,
+        "use_grv_cache" => spec.flag(T::UseGrvCache)?,
+        name => return Err(unknown("transaction", name, TRANSACTION_OPTIONS)),
+    })
+}
+
+// --cluster-string: `description:id@host:port[,host:port...]`, checked just
+// enough to catch a pasted path or a missing port before the client sees it.
+pub fn parse_cluster_string(s: &str) -> Result<String> {
+    let s = s.trim();
+    let invalid = |why: &str| {
+        anyhow!("invalid cluster string '{s}': {why} (expected description:id@host:port[,host:port...])")
+    };
+    let (name, addrs) = s.split_once('@').ok_or_else(|| invalid("no '@'"))?;
+    let (desc, id) = name
+        .split_once(':')
+        .ok_or_else(|| invalid("no ':' between description and id"))?;
+    let word = |w: &str| !w.is_empty() && w.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !word(desc) || !word(id) {
+        return Err(invalid(
+            "description and id must be letters, digits, or '_'",
+        ));
+    }
+    for addr in addrs.split(',') {
+        // host:port, optionally followed by :tls.
+        let addr = addr.trim().strip_suffix(":tls").unwrap_or(addr.trim());
+        let port = addr
+            .rsplit_once(':')
+            .map(|(host, port)| (!host.is_empty(), port));
+        if !matches!(port, Some((true, p)) if p.parse::<u16>().is_ok()) {
+            return Err(invalid(&format!("'{addr}' is not host:port")));
+        }
+    }
+    Ok(s.to_string())
+}
+
+// FoundationDB rejects transaction tags longer than this many bytes.
+const MAX_TAG_LENGTH: usize = 16;
+
+// --tag: validated up front so a bad tag fails before connecting.
+pub fn parse_tag(s: &str) -> Result<String> {
+    if s.is_empty() {
+        return Err(anyhow!("tag must not be empty"));
+    }
+    if s.len() > MAX_TAG_LENGTH {
+        return Err(anyhow!(
+            "tag '{s}' is {} bytes; tags are at most {MAX_TAG_LENGTH}",
+            s.len()
+        ));
+    }
+    Ok(s.to_string())
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+// Destination for command output. Formatting code styles text unconditionally
+// with owo-colors; when color is off the ANSI escape sequences are stripped here
+// on the way out, so files and NO_COLOR terminals get plain text.
+pub struct Output {
+    inner: Box<dyn Write>,
+    color: bool,
+    in_escape: bool,
+    // The pager `inner` feeds, if any; see `pager`.
+    pager: Option<Child>,
+}
+
+impl Output {
+    pub fn stdout() -> Self {
+        Self {
+            inner: Box::new(io::stdout()),
+            color: std::env::var_os("NO_COLOR").is_none(),
+            in_escape: false,
+            pager: None,
+        }
+    }
+
+    pub fn no_color(mut self) -> Self {
+        self.color = false;
+        self
+    }
+
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    pub fn set_color(&mut self, on: bool) {
+        self.color = on;
+    }
+
+    // Output piped into $PAGER (default `less`), or None if it cannot be started.
+    // Like git, LESS=FRX is set unless the user has their own: one-screen output
+    // prints directly, colors pass through, and the screen is not cleared.
+    pub fn pager(color: bool) -> Option<Self> {
+        let cmd = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+        let mut words = shell_words::split(&cmd).ok()?.into_iter();
+        let program = words.next()?;
+        let mut child = Command::new(program)
+            .args(words)
+            .env(
+                "LESS",
+                std::env::var("LESS").unwrap_or_else(|_| "FRX".to_string()),
+            )
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok()?;
+        Some(Self {
+            inner: Box::new(child.stdin.take()?),
+            color,
+            in_escape: false,
+            pager: Some(child),
+        })
+    }
+
+    pub fn is_pager(&self) -> bool {
+        self.pager.is_some()
+    }
+
+    // Flushes, then closes the pager's input and waits for the user to quit it.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()?;
+        self.close_pager()
+    }
+
+    fn close_pager(&mut self) -> io::Result<()> {
+        if let Some(mut child) = self.pager.take() {
+            self.inner = Box::new(io::sink());
+            child.wait()?;
+        }
+        Ok(())
+    }
+
+    pub fn file(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            inner: Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+            color: false,
+            in_escape: false,
+            pager: None,
+        })
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.write_inner(buf) {
+            // Quitting the pager early closes the pipe; the rest of the output is dropped.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe && self.pager.is_some() => {
+                Ok(buf.len())
+            }
+            res => res,
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.flush() {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe && self.pager.is_some() => Ok(()),
+            res => res,
+        }
+    }
+}
+
+impl Output {
+    fn write_inner(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.color {
+            return self.inner.write(buf);
+        }
+        // Escape state carries across calls since a sequence may be split between writes.
+        let mut plain = Vec::with_capacity(buf.len());
+        for &b in buf {
+            if self.in_escape {
+                // CSI sequences end with a byte in 0x40..=0x7e; owo-colors only emits SGR ("...m").
+                if (0x40..=0x7e).contains(&b) && b != b'[' {
+                    self.in_escape = false;
+                }
+            } else if b == 0x1b {
+                self.in_escape = true;
+            } else {
+                plain.push(b);
+            }
+        }
+        self.inner.write_all(&plain)?;
+        Ok(buf.len())
+    }
+}
+
+// A pager left running when output is dropped (say, by an error) would fight
+// the shell for the terminal.
+impl Drop for Output {
+    fn drop(&mut self) {
+        let _ = self.close_pager();
+    }
+}
@@ -0,0 +1,951 @@
+use crate::format::{SchemaField, B64_ALPHABET, SCHEMA_TYPES};
+use anyhow::{anyhow, Result};
+use foundationdb::options::StreamingMode;
+use foundationdb::tuple::{Element, Versionstamp};
+use foundationdb::KeySelector;
+
+// Splits a path on '/', skipping empty segments. Double quotes make a segment
+// literal, so names that are empty or contain '/' can be written: /app/"a/b"/"".
+// Inside quotes, \" and \\ stand for a quote and a backslash. Unquoted `.`
+// segments are dropped and `..` removes the segment before it (nothing at
+// the root), so /app/../app/./foo is /app/foo.
+pub fn parse_path(s: &str) -> Vec<String> {
+    parse_path_pattern(s).names()
+}
+
+// A path that may name several directories: each segment is a name and
+// whether it is a glob pattern.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathPattern {
+    pub segments: Vec<(String, bool)>,
+}
+
+impl PathPattern {
+    pub fn names(&self) -> Vec<String> {
+        self.segments.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    pub fn has_glob(&self) -> bool {
+        self.segments.iter().any(|&(_, glob)| glob)
+    }
+}
+
+// Every segment a literal name.
+impl From<Vec<String>> for PathPattern {
+    fn from(names: Vec<String>) -> Self {
+        Self {
+            segments: names.into_iter().map(|name| (name, false)).collect(),
+        }
+    }
+}
+
+// Names that are glob patterns unless quoted.
+pub const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+// parse_path, also noting which segments are globs: unquoted ones with `*`,
+// `?` or `[`. Quoting makes such a name literal: /app/"a[1]".
+pub fn parse_path_pattern(s: &str) -> PathPattern {
+    let mut path = PathPattern::default();
+    let mut name = String::new();
+    // A quoted segment is kept even when empty.
+    let mut quoted = false;
+    let mut chars = s.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' => {
+                push_segment(&mut path, std::mem::take(&mut name), quoted);
+                quoted = false;
+            }
+            '"' => {
+                quoted = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => name.extend(chars.next()),
+                        c => name.push(c),
+                    }
+                }
+            }
+            c => name.push(c),
+        }
+    }
+    push_segment(&mut path, name, quoted);
+    path
+}
+
+fn push_segment(path: &mut PathPattern, name: String, quoted: bool) {
+    if quoted {
+        path.segments.push((name, false));
+        return;
+    }
+    match name.as_str() {
+        "" | "." => {}
+        ".." => {
+            path.segments.pop();
+        }
+        _ => {
+            let glob = name.contains(GLOB_CHARS);
+            path.segments.push((name, glob));
+        }
+    }
+}
+
+pub const STREAMING_MODES: &[&str] = &[
+    "auto", "want-all", "iterator", "exact", "small", "medium", "large", "serial",
+];
+
+// Streaming mode by name; "auto" (None) leaves the choice to each read.
+pub fn parse_streaming_mode(name: &str) -> Result<Option<StreamingMode>> {
+    Ok(Some(match name {
+        "auto" => return Ok(None),
+        "want-all" => StreamingMode::WantAll,
+        "iterator" => StreamingMode::Iterator,
+        "exact" => StreamingMode::Exact,
+        "small" => StreamingMode::Small,
+        "medium" => StreamingMode::Medium,
+        "large" => StreamingMode::Large,
+        "serial" => StreamingMode::Serial,
+        _ => {
+            return Err(anyhow!(
+                "unknown streaming mode '{name}' (expected {})",
+                STREAMING_MODES.join(", ")
+            ))
+        }
+    }))
+}
+
+// --schema: comma-separated `name[:type]` per tuple position, e.g. "idx:int, name:string".
+pub fn parse_schema(s: &str) -> Result<Vec<SchemaField>> {
+    s.split(',')
+        .map(|field| {
+            let (name, kind) = match field.split_once(':') {
+                Some((name, kind)) => (name.trim(), Some(kind.trim())),
+                None => (field.trim(), None),
+            };
+            if name.is_empty() {
+                return Err(anyhow!(
+                    "empty field name in schema '{s}' (expected e.g. \"idx:int, name:string\")"
+                ));
+            }
+            let kind = match kind {
+                None | Some("") => None,
+                Some(k) => Some(
+                    *SCHEMA_TYPES
+                        .iter()
+                        .find(|t| **t == k.to_ascii_lowercase())
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "unknown type '{k}' for field '{name}' (have: {})",
+                                SCHEMA_TYPES.join(", ")
+                            )
+                        })?,
+                ),
+            };
+            Ok(SchemaField {
+                name: name.to_string(),
+                kind,
+            })
+        })
+        .collect()
+}
+
+// --max-scan-bytes: a size like 500MB, 2GB, or a plain byte count. Units are
+// powers of 1000, matching how sizes are printed.
+pub fn parse_byte_size(s: &str) -> Result<u64> {
+    let t = s.trim();
+    let split = t
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(t.len());
+    let (number, unit) = t.split_at(split);
+    let invalid = || anyhow!("invalid size '{s}' (expected e.g. 500MB or 2GB)");
+    let scale: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1_000,
+        "M" | "MB" => 1_000_000,
+        "G" | "GB" => 1_000_000_000,
+        "T" | "TB" => 1_000_000_000_000,
+        _ => return Err(invalid()),
+    };
+    // Whole numbers stay exact; fractions go through f64 (1.5GB).
+    let bytes = match number.parse::<u64>() {
+        Ok(n) => n.checked_mul(scale),
+        Err(_) => {
+            let n: f64 = number.parse().map_err(|_| invalid())?;
+            let bytes = n * scale as f64;
+            (bytes < u64::MAX as f64).then_some(bytes as u64)
+        }
+    };
+    bytes.ok_or_else(|| anyhow!("size '{s}' is too large"))
+}
+
+// --begin-sel / --end-sel: a key selector over a key relative to the
+// directory, written `<kind>[+n|-n]:<key>`, where kind is firstGreaterOrEqual,
+// firstGreaterThan, lastLessOrEqual, or lastLessThan, the optional offset moves
+// that many keys further, and the key is a byte or tuple literal, e.g.
+// `firstGreaterThan:("users", 42)` or `lastLessThan+1:abc`.
+#[derive(Clone, Debug)]
+pub struct SelectorSpec {
+    pub key: Vec<u8>,
+    pub or_equal: bool,
+    pub offset: i32,
+    // As written, for the scan header.
+    pub text: String,
+}
+
+impl SelectorSpec {
+    pub(crate) fn resolve(&self, dir_prefix: &[u8]) -> KeySelector<'static> {
+        let mut key = dir_prefix.to_vec();
+        key.extend_from_slice(&self.key);
+        KeySelector::new(key.into(), self.or_equal, self.offset)
+    }
+}
+
+const SELECTOR_KINDS: &str = "firstGreaterOrEqual, firstGreaterThan, lastLessOrEqual, lastLessThan";
+
+pub fn parse_selector(s: &str) -> Result<SelectorSpec> {
+    let (head, key) = s.split_once(':').ok_or_else(|| {
+        anyhow!("invalid selector '{s}': expected <kind>[+n|-n]:<key>, e.g. firstGreaterThan:abc")
+    })?;
+    let (kind, offset) = match head.find(['+', '-']) {
+        Some(i) => {
+            let n: i32 = head[i..]
+                .parse()
+                .map_err(|_| anyhow!("invalid selector offset '{}' in '{s}'", &head[i..]))?;
+            (&head[..i], n)
+        }
+        None => (head, 0),
+    };
+    // (or_equal, offset) as FoundationDB defines each kind.
+    let (or_equal, base) = match kind.trim().to_ascii_lowercase().replace('_', "").as_str() {
+        "firstgreaterorequal" => (false, 1),
+        "firstgreaterthan" => (true, 1),
+        "lastlessorequal" => (true, 0),
+        "lastlessthan" => (false, 0),
+        _ => {
+            return Err(anyhow!(
+                "unknown selector kind '{kind}' (have: {SELECTOR_KINDS})"
+            ))
+        }
+    };
+    let offset = offset
+        .checked_add(base)
+        .ok_or_else(|| anyhow!("selector offset out of range in '{s}'"))?;
+    Ok(SelectorSpec {
+        key: parse_key_literal(key).map_err(|e| anyhow!("invalid key in selector '{s}': {e}"))?,
+        or_equal,
+        offset,
+        text: s.to_string(),
+    })
+}
+
+// --fields: comma-separated element indices, e.g. 0,2.
+pub fn parse_fields(s: &str) -> Result<Vec<usize>> {
+    s.split(',')
+        .map(|f| {
+            f.trim()
+                .parse()
+                .map_err(|_| anyhow!("invalid field index '{f}' (expected e.g. 0,2)"))
+        })
+        .collect()
+}
+
+// Standard alphabet; trailing `=` padding is optional.
+fn decode_b64(s: &str) -> Result<Vec<u8>> {
+    let body = s.trim_end_matches('=');
+    let mut out = Vec::with_capacity(body.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in body.bytes() {
+        let v = B64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| anyhow!("invalid base64 character '{}'", c as char))?;
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if body.len() % 4 == 1 {
+        return Err(anyhow!("invalid base64 length"));
+    }
+    // Padding is optional, but when present it must complete the last quantum.
+    let padding = s.len() - body.len();
+    if padding > 2 || (padding > 0 && !(body.len() + padding).is_multiple_of(4)) {
+        return Err(anyhow!("invalid base64 padding"));
+    }
+    Ok(out)
+}
+
+pub fn parse_bytes_literal(s: &str) -> Result<Vec<u8>> {
+    if let Some(b64) = s.strip_prefix("b64:") {
+        return decode_b64(b64);
+    }
+    let mut out = Vec::with_capacity(s.len());
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\\' {
+            let Some(&n) = bytes.get(i + 1) else {
+                return Err(anyhow!("trailing '\\' (write \\\\ for a backslash)"));
+            };
+            match n {
+                b'x' | b'X' => {
+                    if i + 3 >= bytes.len() {
+                        return Err(anyhow!("incomplete hex escape"));
+                    }
+                    let h1 = bytes[i + 2] as char;
+                    let h2 = bytes[i + 3] as char;
+                    let v = (hex_val(h1)? << 4) | hex_val(h2)?;
+                    out.push(v);
+                    i += 4;
+                    continue;
+                }
+                b'u' => {
+                    // \u{XXXX}: a code point, stored as its UTF-8 bytes.
+                    let rest = &s[i + 2..];
+                    let body = rest
+                        .strip_prefix('{')
+                        .and_then(|r| r.split_once('}'))
+                        .map(|(hex, _)| hex)
+                        .ok_or_else(|| {
+                            anyhow!("incomplete unicode escape (expected \\u{{XXXX}})")
+                        })?;
+                    if body.is_empty()
+                        || body.len() > 6
+                        || !body.chars().all(|c| c.is_ascii_hexdigit())
+                    {
+                        return Err(anyhow!("invalid unicode escape \\u{{{body}}}"));
+                    }
+                    let cp = u32::from_str_radix(body, 16)?;
+                    let ch = char::from_u32(cp).ok_or_else(|| {
+                        anyhow!("invalid unicode escape \\u{{{body}}}: surrogate or out of range")
+                    })?;
+                    let mut buf = [0u8; 4];
+                    out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                    i += 2 + body.len() + 2;
+                    continue;
+                }
+                b'n' => {
+                    out.push(b'\n');
+                    i += 2;
+                    continue;
+                }
+                b'r' => {
+                    out.push(b'\r');
+                    i += 2;
+                    continue;
+                }
+                b't' => {
+                    out.push(b'\t');
+                    i += 2;
+                    continue;
+                }
+                b'\\' => {
+                    out.push(b'\\');
+                    i += 2;
+                    continue;
+                }
+                // `\(` keeps a leading paren from reading as a tuple literal.
+                b'"' | b'(' => {
+                    out.push(n);
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    let esc = s[i + 1..].chars().next().unwrap_or_default();
+                    return Err(anyhow!("unknown escape '\\{esc}'"));
+                }
+            }
+        }
+        out.push(c);
+        i += 1;
+    }
+    Ok(out)
+}
+
+// Parses a key or prefix argument: a tuple literal like `("users", 42)` is
+// packed as a tuple, anything else is a raw byte literal (a leading `(` can be
+// escaped as `\(` to force raw bytes).
+pub fn parse_key_literal(s: &str) -> Result<Vec<u8>> {
+    if s.trim_start().starts_with('(') {
+        Ok(foundationdb::tuple::pack(&parse_tuple_literal(s)?))
+    } else {
+        parse_bytes_literal(s)
+    }
+}
+
+// --prefix-tuple: a single tuple element, packed as the one-element tuple
+// (elem,) so a prefix range ends on a tuple boundary. Element syntax is as
+// inside a tuple literal (42, "a b", uuid:...); anything else is a plain
+// string, so `users` means ("users",).
+pub fn parse_prefix_element(s: &str) -> Result<Vec<u8>> {
+    let mut p = TupleParser {
+        chars: s.chars().collect(),
+        pos: 0,
+    };
+    let el = match p.element() {
+        Ok(el) if p.pos == p.chars.len() => el,
+        _ => Element::String(s.to_string().into()),
+    };
+    Ok(foundationdb::tuple::pack(&vec![el]))
+}
+
+// Parses a tuple literal such as `("users", 42, true, (1.5, nil), b"\x00")`
+// into its elements. The syntax mirrors `format_element`'s output.
+pub fn parse_tuple_literal(s: &str) -> Result<Vec<Element<'static>>> {
+    let mut p = TupleParser {
+        chars: s.chars().collect(),
+        pos: 0,
+    };
+    p.skip_ws();
+    let items = p.tuple()?;
+    p.skip_ws();
+    if p.pos < p.chars.len() {
+        return Err(anyhow!("unexpected trailing input at offset {}", p.pos));
+    }
+    Ok(items)
+}
+
+struct TupleParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl TupleParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(anyhow!("expected '{c}' at offset {}", self.pos))
+        }
+    }
+
+    fn tuple(&mut self) -> Result<Vec<Element<'static>>> {
+        self.expect('(')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(')') {
+                self.pos += 1;
+                return Ok(items);
+            }
+            items.push(self.element()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {}
+                _ => return Err(anyhow!("expected ',' or ')' at offset {}", self.pos)),
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Element<'static>> {
+        match self.peek() {
+            Some('(') => Ok(Element::Tuple(self.tuple()?)),
+            Some('"') => Ok(Element::String(self.quoted()?.into())),
+            Some('b') if self.chars.get(self.pos + 1) == Some(&'"') => {
+                self.pos += 1;
+                let raw = self.quoted_raw()?;
+                Ok(Element::Bytes(parse_bytes_literal(&raw)?.into()))
+            }
+            Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.number(),
+            Some(_) => self.word(),
+            None => Err(anyhow!("unexpected end of input")),
+        }
+    }
+
+    // Body of a quoted string with escapes left in place (for byte literals).
+    fn quoted_raw(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    out.push(c);
+                    if let Some(n) = self.peek() {
+                        out.push(n);
+                        self.pos += 1;
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        Err(anyhow!("unterminated string"))
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let n = self.peek().ok_or_else(|| anyhow!("unterminated string"))?;
+                    self.pos += 1;
+                    out.push(match n {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'x' => self.hex_escape()?,
+                        'u' => self.unicode_escape()?,
+                        other => other,
+                    });
+                }
+                _ => out.push(c),
+            }
+        }
+        Err(anyhow!("unterminated string"))
+    }
+
+    // `\xHH` in a string: an ASCII character (others are written `\u{..}`).
+    fn hex_escape(&mut self) -> Result<char> {
+        let digits = self.chars.get(self.pos..self.pos + 2).unwrap_or_default();
+        let [h1, h2] = digits else {
+            return Err(anyhow!("incomplete hex escape"));
+        };
+        let v = (hex_val(*h1)? << 4) | hex_val(*h2)?;
+        if !v.is_ascii() {
+            return Err(anyhow!(
+                "\\x{v:02x} is not ASCII; write \\u{{{v:x}}} in strings"
+            ));
+        }
+        self.pos += 2;
+        Ok(v as char)
+    }
+
+    // `\u{XXXX}` in a string.
+    fn unicode_escape(&mut self) -> Result<char> {
+        self.expect('{')?;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let body: String = self.chars[start..self.pos].iter().collect();
+        self.expect('}')?;
+        u32::from_str_radix(&body, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| anyhow!("invalid unicode escape \\u{{{body}}}"))
+    }
+
+    fn number(&mut self) -> Result<Element<'static>> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+        {
+            self.pos += 1;
+        }
+        let tok: String = self.chars[start..self.pos].iter().collect();
+        let (neg, digits) = match tok.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, tok.strip_prefix('+').unwrap_or(&tok)),
+        };
+        if let Some(h) = digits.strip_prefix("0x") {
+            let v = u64::from_str_radix(h, 16).ok().and_then(|v| {
+                if neg {
+                    0i64.checked_sub_unsigned(v)
+                } else {
+                    i64::try_from(v).ok()
+                }
+            });
+            return Ok(Element::Int(
+                v.ok_or_else(|| anyhow!("invalid integer: {tok}"))?,
+            ));
+        }
+        if let Some(f) = tok.strip_suffix("f32") {
+            return Ok(Element::Float(
+                f.parse().map_err(|_| anyhow!("invalid float: {tok}"))?,
+            ));
+        }
+        if let Some(d) = tok.strip_suffix("f64") {
+            return Ok(Element::Double(
+                d.parse().map_err(|_| anyhow!("invalid double: {tok}"))?,
+            ));
+        }
+        if tok.contains(['.', 'e', 'E']) {
+            return Ok(Element::Double(
+                tok.parse().map_err(|_| anyhow!("invalid double: {tok}"))?,
+            ));
+        }
+        Ok(Element::Int(
+            tok.parse().map_err(|_| anyhow!("invalid integer: {tok}"))?,
+        ))
+    }
+
+    fn word(&mut self) -> Result<Element<'static>> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':'))
+        {
+            self.pos += 1;
+        }
+        let tok: String = self.chars[start..self.pos].iter().collect();
+        match tok.as_str() {
+            "versionstamp" if self.peek() == Some('(') => self.versionstamp_fields(),
+            "nil" | "null" => Ok(Element::Nil),
+            "true" => Ok(Element::Bool(true)),
+            "false" => Ok(Element::Bool(false)),
+            _ => {
+                if let Some(u) = tok.strip_prefix("uuid:") {
+                    let u = foundationdb::tuple::Uuid::parse_str(u)
+                        .map_err(|e| anyhow!("invalid uuid: {e}"))?;
+                    return Ok(Element::Uuid(u));
+                }
+                if let Some(h) = tok.strip_prefix("versionstamp:") {
+                    let b: [u8; 12] = hex::decode(h)
+                        .ok()
+                        .and_then(|b| b.try_into().ok())
+                        .ok_or_else(|| anyhow!("versionstamp needs 12 hex-encoded bytes"))?;
+                    let mut tr = [0u8; 10];
+                    tr.copy_from_slice(&b[..10]);
+                    let user = u16::from_be_bytes([b[10], b[11]]);
+                    return Ok(Element::Versionstamp(Versionstamp::complete(tr, user)));
+                }
+                Err(anyhow!("unexpected token '{tok}' at offset {start}"))
+            }
+        }
+    }
+
+    // `(v=<u64>, batch=<u16>, user=<u16>)` or `(incomplete, user=<u16>)`, as
+    // format_element prints versionstamps.
+    fn versionstamp_fields(&mut self) -> Result<Element<'static>> {
+        self.expect('(')?;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != ')') {
+            self.pos += 1;
+        }
+        let body: String = self.chars[start..self.pos].iter().collect();
+        self.expect(')')?;
+        let (mut version, mut batch, mut user, mut incomplete) = (None, None, 0u16, false);
+        for field in body.split(',').map(str::trim) {
+            let invalid = || anyhow!("invalid versionstamp field '{field}'");
+            match field.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                Some(("v", n)) => version = Some(n.parse::<u64>().map_err(|_| invalid())?),
+                Some(("batch", n)) => batch = Some(n.parse::<u16>().map_err(|_| invalid())?),
+                Some(("user", n)) => user = n.parse().map_err(|_| invalid())?,
+                None if field == "incomplete" => incomplete = true,
+                _ => return Err(invalid()),
+            }
+        }
+        if incomplete {
+            return Ok(Element::Versionstamp(Versionstamp::incomplete(user)));
+        }
+        let (Some(version), Some(batch)) = (version, batch) else {
+            return Err(anyhow!(
+                "versionstamp needs v= and batch= (or 'incomplete')"
+            ));
+        };
+        let mut tr = [0u8; 10];
+        tr[..8].copy_from_slice(&version.to_be_bytes());
+        tr[8..].copy_from_slice(&batch.to_be_bytes());
+        Ok(Element::Versionstamp(Versionstamp::complete(tr, user)))
+    }
+}
+
+fn hex_val(c: char) -> Result<u8> {
+    match c {
+        '0'..='9' => Ok((c as u8) - b'0'),
+        'a'..='f' => Ok((c as u8) - b'a' + 10),
+        'A'..='F' => Ok((c as u8) - b'A' + 10),
+        _ => Err(anyhow!("invalid hex digit")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::format_b64;
+
+    #[test]
+    fn b64_round_trips() {
+        for len in 0..=7 {
+            let bytes: Vec<u8> = (0..len).map(|i| 0xf0 ^ (i as u8 * 37)).collect();
+            let text = format_b64(&bytes, 0);
+            assert_eq!(parse_bytes_literal(&text).unwrap(), bytes, "{text}");
+            // The padding is optional on input.
+            let unpadded = text.trim_end_matches('=');
+            assert_eq!(decode_b64(&unpadded[4..]).unwrap(), bytes, "{unpadded}");
+        }
+    }
+
+    #[test]
+    fn b64_rejects_invalid_input() {
+        for bad in [
+            "Zg=", "Zm9==", "Zg===", "Zm8==", "Z", "Zm9vY", "Zm=9", "Zm9v!", "====",
+        ] {
+            assert!(decode_b64(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn bytes_literal_escapes() {
+        assert_eq!(
+            parse_bytes_literal(r#"a\x00\n\\\"\("#).unwrap(),
+            b"a\x00\n\\\"(".to_vec()
+        );
+        // Code points are stored as their UTF-8 bytes, up to four of them.
+        assert_eq!(parse_bytes_literal(r"\u{e9}").unwrap(), "é".as_bytes());
+        assert_eq!(parse_bytes_literal(r"\u{20ac}x").unwrap(), "€x".as_bytes());
+        assert_eq!(parse_bytes_literal(r"\u{1F600}").unwrap(), "😀".as_bytes());
+    }
+
+    #[test]
+    fn bytes_literal_rejects_bad_escapes() {
+        for bad in [
+            r"\q",
+            r"ab\",
+            r"\x0",
+            r"\u{d800}",
+            r"\u{DFFF}",
+            r"\u{110000}",
+            r"\u{}",
+            r"\u{e9",
+        ] {
+            assert!(parse_bytes_literal(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn path_dot_segments() {
+        let p = |s: &str| parse_path(s);
+        assert_eq!(p("/app/../app/./foo"), ["app", "foo"]);
+        assert_eq!(p("/app/foo/.."), ["app"]);
+        assert_eq!(p("./a//b/"), ["a", "b"]);
+        // `..` above the root stays at the root.
+        assert!(p("/..").is_empty());
+        assert_eq!(p("/../../a/../../b"), ["b"]);
+        // Quoted, they are ordinary names.
+        assert_eq!(p(r#"/"."/"..""#), [".", ".."]);
+    }
+
+    #[test]
+    fn quoted_segments_are_not_globs() {
+        let globs = |s: &str| -> Vec<bool> {
+            let path = parse_path_pattern(s);
+            path.segments.iter().map(|&(_, glob)| glob).collect()
+        };
+        assert_eq!(globs("/app/*/config"), [false, true, false]);
+        assert_eq!(globs(r#"/app/"a[1]"/"x*""#), [false, false, false]);
+        assert_eq!(globs(r#"/"a"?/b"#), [false, false]);
+        assert_eq!(parse_path(r#"/app/"a[1]""#), ["app", "a[1]"]);
+        // `..` drops the glob along with its name.
+        assert!(!parse_path_pattern("/app/*/..").has_glob());
+    }
+
+    #[test]
+    fn tuple_literals_round_trip() {
+        use crate::format::{format_element, FormatOptions};
+        let uuid = foundationdb::tuple::Uuid::from_u128(0x0123_4567_89ab_cdef_0011_2233_4455_6677);
+        let elements = vec![
+            Element::Nil,
+            Element::Bool(true),
+            Element::Bool(false),
+            Element::Int(0),
+            Element::Int(-42),
+            Element::Int(i64::MAX),
+            Element::Int(i64::MIN),
+            Element::Float(1.5),
+            Element::Float(-0.25),
+            Element::Double(3.0),
+            Element::Double(-1e-300),
+            Element::String("a \"b\" c\n".into()),
+            Element::Bytes(b"\x00\xffab\\\"".to_vec().into()),
+            Element::Bytes(Vec::new().into()),
+            Element::Uuid(uuid),
+            Element::Versionstamp(Versionstamp::complete([1, 2, 3, 4, 5, 6, 7, 8, 0, 9], 7)),
+            Element::Versionstamp(Versionstamp::incomplete(3)),
+            Element::Tuple(vec![]),
+            Element::Tuple(vec![Element::Int(1), Element::Tuple(vec![Element::Nil])]),
+        ];
+        let tuple = Element::Tuple(elements.clone());
+        let options = [
+            FormatOptions::default(),
+            FormatOptions {
+                int_hex: true,
+                ..FormatOptions::default()
+            },
+            FormatOptions {
+                pretty: true,
+                ..FormatOptions::default()
+            },
+        ];
+        for fmt in &options {
+            let text = format_element(&tuple, fmt);
+            assert_eq!(parse_tuple_literal(&text).unwrap(), elements, "{text}");
+        }
+        // Raw versionstamp bytes parse back too, but only when complete.
+        let vs_hex = FormatOptions {
+            vs_hex: true,
+            ..FormatOptions::default()
+        };
+        let complete = Element::Tuple(vec![elements[15].clone()]);
+        let text = format_element(&complete, &vs_hex);
+        assert_eq!(
+            parse_tuple_literal(&text).unwrap(),
+            vec![elements[15].clone()]
+        );
+    }
+
+    #[test]
+    fn tuple_literal_syntax() {
+        let p = |s: &str| parse_tuple_literal(s).unwrap();
+        assert_eq!(p(" ( 1 , null ,) "), vec![Element::Int(1), Element::Nil]);
+        assert_eq!(
+            p("(0x1f, -0x10, +7)")[..],
+            [Element::Int(31), Element::Int(-16), Element::Int(7)]
+        );
+        assert_eq!(
+            p("(1.5, 2e3)")[..],
+            [Element::Double(1.5), Element::Double(2000.0)]
+        );
+        assert_eq!(p(r#"("\x41\u{e9}")"#), vec![Element::String("Aé".into())]);
+    }
+
+    #[test]
+    fn tuple_literal_rejects_malformed_input() {
+        for bad in [
+            "",
+            "1",
+            "(",
+            "(1",
+            "(1, (2)",
+            "(1))",
+            "(1) x",
+            "(1 2)",
+            "(,)",
+            "(\"open)",
+            r#"("\x")"#,
+            r#"("\xe9")"#,
+            r#"("\u{d800}")"#,
+            r#"("\u{}")"#,
+            r#"(b"\q")"#,
+            "(0x)",
+            "(0xffffffffffffffff)",
+            "(12abc)",
+            "(1.2.3)",
+            "(yes)",
+            "(uuid:nope)",
+            "(versionstamp:00ff)",
+            "(versionstamp(v=1))",
+            "(versionstamp(v=1, batch=70000, user=0))",
+        ] {
+            assert!(parse_tuple_literal(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn selectors_map_to_key_selectors() {
+        let named = [
+            (
+                "firstGreaterOrEqual",
+                KeySelector::first_greater_or_equal(&b"k"[..]),
+            ),
+            (
+                "firstGreaterThan",
+                KeySelector::first_greater_than(&b"k"[..]),
+            ),
+            (
+                "lastLessOrEqual",
+                KeySelector::last_less_or_equal(&b"k"[..]),
+            ),
+            ("lastLessThan", KeySelector::last_less_than(&b"k"[..])),
+        ];
+        for (kind, expected) in named {
+            for (shift, delta) in [("", 0), ("+3", 3), ("-2", -2), ("+0", 0)] {
+                let spec = parse_selector(&format!("{kind}{shift}:k")).unwrap();
+                let sel = spec.resolve(b"\x15\x01");
+                assert_eq!(sel.key(), b"\x15\x01k", "{kind}{shift}");
+                assert_eq!(sel.or_equal(), expected.or_equal(), "{kind}{shift}");
+                assert_eq!(sel.offset(), expected.offset() + delta, "{kind}{shift}");
+            }
+        }
+        // The kind is case- and underscore-insensitive.
+        for kind in [
+            "first_greater_than",
+            "FIRSTGREATERTHAN",
+            " firstGreaterThan ",
+        ] {
+            let spec = parse_selector(&format!("{kind}:k")).unwrap();
+            assert!(spec.or_equal && spec.offset == 1, "{kind}");
+        }
+        // Everything after the first ':' is the key, as a byte or tuple literal.
+        let spec = parse_selector("lastLessThan:a:b").unwrap();
+        assert_eq!(spec.key, b"a:b");
+        let spec = parse_selector(r#"firstGreaterThan-1:("users", 42)"#).unwrap();
+        assert_eq!(spec.key, foundationdb::tuple::pack(&("users", 42)));
+        assert_eq!((spec.or_equal, spec.offset), (true, 0));
+    }
+
+    #[test]
+    fn selector_errors() {
+        for bad in [
+            "firstGreaterThan",
+            "firstGreater:k",
+            ":k",
+            "firstGreaterThan+:k",
+            "firstGreaterThan+x:k",
+            "firstGreaterThan+1-1:k",
+            "firstGreaterThan+2147483647:k",
+            "lastLessThan-2147483649:k",
+            r#"firstGreaterThan:("open"#,
+            r"firstGreaterThan:\q",
+        ] {
+            assert!(parse_selector(bad).is_err(), "{bad} was accepted");
+        }
+    }
+
+    #[test]
+    fn byte_sizes() {
+        for (text, bytes) in [
+            ("0", 0),
+            ("1234", 1234),
+            ("12B", 12),
+            ("500MB", 500_000_000),
+            ("500mb", 500_000_000),
+            ("2 GB", 2_000_000_000),
+            (" 3k ", 3_000),
+            ("1.5G", 1_500_000_000),
+            (".5KB", 500),
+            ("2TB", 2_000_000_000_000),
+            ("18446744073709551615", u64::MAX),
+        ] {
+            assert_eq!(parse_byte_size(text).unwrap(), bytes, "{text}");
+        }
+    }
+
+    #[test]
+    fn byte_sizes_reject_bad_input() {
+        for bad in [
+            "", " ", "MB", "-5", "-5MB", "+5", "5PB", "5 M B", "1.2.3", ".", "1e3", "5MiB",
+        ] {
+            assert!(parse_byte_size(bad).is_err(), "{bad} was accepted");
+        }
+        for huge in [
+            "18446744073709551616",
+            "18446744073709552TB",
+            "20000000000GB",
+            "99999999.5TB",
+        ] {
+            let err = parse_byte_size(huge).unwrap_err().to_string();
+            assert!(err.contains("too large"), "{huge}: {err}");
+        }
+    }
+}
@@ -1,21 +1,194 @@
-use crate::util::{display_path, parse_path};
-use anyhow::Result;
-use foundationdb::directory::{Directory, DirectoryLayer};
+use crate::format::{display_path, FormatOptions, ValueAs};
+use crate::output::Output;
+use crate::parse::{parse_path, parse_path_pattern, PathPattern};
+use crate::util::{
+    describe_error, Client, LsOptions, LsSort, OutputFormat, RawScanOptions, ReadOptions,
+    ScanFormat, ScanOptions, Scanned, Source,
+};
+use anyhow::{anyhow, Context as _, Result};
+use foundationdb::Transaction;
 use owo_colors::OwoColorize;
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
 use rustyline::validate::Validator;
-use rustyline::Editor;
-use rustyline::{Context, Helper};
+use rustyline::{Config, Context, EditMode, Editor, Helper};
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tokio::task;
+use std::time::{Duration, Instant};
+
+// Entries kept in the history file.
+const HISTORY_SIZE: usize = 1000;
+
+// Where history lived before it moved to the data directory; still read when
+// the new file doesn't exist yet, so upgrading keeps old entries.
+const LEGACY_HISTORY_FILE: &str = ".fdbdir_history";
+
+// REPL history file: --history-file, then $FDBDIR_HISTORY, then fdbdir/history
+// under the XDG data directory (~/.local/share on Linux).
+pub fn history_path(flag: Option<String>) -> PathBuf {
+    flag.or_else(|| {
+        std::env::var("FDBDIR_HISTORY")
+            .ok()
+            .filter(|s| !s.is_empty())
+    })
+    .map(PathBuf::from)
+    .or_else(|| dirs::data_dir().map(|d| d.join("fdbdir").join("history")))
+    .unwrap_or_else(|| PathBuf::from(LEGACY_HISTORY_FILE))
+}
+
+// Children per directory for path completion. Completion runs synchronously
+// inside readline, so it only reads this cache and never waits on the cluster.
+type ChildCache = Arc<Mutex<HashMap<Vec<String>, (Instant, Vec<String>)>>>;
+
+// Cached listings older than this are refreshed on the next completion.
+const CHILD_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// Next tuple elements seen under a (directory, packed key prefix), with whether
+// keys continue past them; filled in the background like ChildCache.
+type ElementCache = Arc<Mutex<HashMap<(Vec<String>, Vec<u8>), (Instant, Vec<(String, bool)>)>>>;
+
+// Distinct elements sampled per key completion, to keep each refresh to a few reads.
+const KEY_SAMPLE: usize = 50;
+
+// Commands paged automatically with `set pager on`; `--pager` pages any command.
+const PAGED: &[&str] = &[
+    "ls",
+    "scan",
+    "dump",
+    "keys",
+    "subspace",
+    "rawscan",
+    "more",
+    "grep",
+    "groupby",
+    "find",
+    "cluster-status",
+];
 
 struct ReplHelper {
-    db: Arc<foundationdb::Database>,
+    db: Arc<Client>,
     cwd: Arc<Mutex<Vec<String>>>,
+    children: ChildCache,
+    elements: ElementCache,
+}
+
+impl ReplHelper {
+    // Returns what is cached (possibly nothing); a missing or stale entry is
+    // refreshed in the background so a later Tab sees it.
+    fn cached_children(&self, path: &[String]) -> Vec<String> {
+        let entry = self.children.lock().unwrap().get(path).cloned();
+        match entry {
+            Some((at, items)) if at.elapsed() < CHILD_CACHE_TTL => items,
+            entry => {
+                let db = self.db.clone();
+                let cache = self.children.clone();
+                let path = path.to_vec();
+                tokio::spawn(async move { refresh_children(&db, &cache, path).await });
+                entry.map(|(_, items)| items).unwrap_or_default()
+            }
+        }
+    }
+
+    fn cached_elements(&self, path: &[String], prefix: Vec<u8>) -> Vec<(String, bool)> {
+        let key = (path.to_vec(), prefix);
+        let entry = self.elements.lock().unwrap().get(&key).cloned();
+        match entry {
+            Some((at, items)) if at.elapsed() < CHILD_CACHE_TTL => items,
+            entry => {
+                let db = self.db.clone();
+                let cache = self.elements.clone();
+                tokio::spawn(async move {
+                    let (path, prefix) = &key;
+                    if let Ok(items) =
+                        crate::util::sample_next_elements(&db, path, prefix, KEY_SAMPLE).await
+                    {
+                        cache.lock().unwrap().insert(key, (Instant::now(), items));
+                    }
+                });
+                entry.map(|(_, items)| items).unwrap_or_default()
+            }
+        }
+    }
+
+    // Tuple-key completion for get/cat/subspace and scan/keys --prefix: offers
+    // the next element of keys in the current directory that share the
+    // elements already typed, e.g. `get '("users", 4<Tab>`.
+    fn complete_key(&self, line: &str) -> Option<(usize, Vec<Pair>)> {
+        let cmd = line.split_whitespace().next()?;
+        let open = line.find('(')?;
+        let before = line[..open].trim_end_matches(['\'', '"']);
+        let wants_key = match cmd {
+            "get" | "cat" | "subspace" | "clear-prefix" => true,
+            "scan" | "dump" | "keys" => before.ends_with("--prefix ") || before.ends_with("-p "),
+            _ => false,
+        };
+        if !wants_key || !before.ends_with(' ') {
+            return None;
+        }
+        // Find the last top-level comma, giving up inside nested or closed tuples.
+        let (mut depth, mut in_str, mut escaped) = (0usize, false, false);
+        let mut last_comma = None;
+        for (i, c) in line[open..].char_indices() {
+            if in_str {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_str = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_str = true,
+                '(' => depth += 1,
+                ')' if depth == 1 => return None,
+                ')' => depth -= 1,
+                ',' if depth == 1 => last_comma = Some(open + i),
+                _ => {}
+            }
+        }
+        if depth != 1 {
+            return None;
+        }
+        let typed = match last_comma {
+            Some(i) => crate::parse::parse_tuple_literal(&format!("{})", &line[open..=i])).ok()?,
+            None => vec![],
+        };
+        let start = last_comma.map_or(open + 1, |i| i + 1);
+        let start = start + (line[start..].len() - line[start..].trim_start().len());
+        let partial = &line[start..];
+        let quote = if line[..open].ends_with('\'') {
+            "'"
+        } else {
+            ""
+        };
+        let cwd = self.cwd.lock().unwrap().clone();
+        let prefix = foundationdb::tuple::pack(&typed);
+        let pairs = self
+            .cached_elements(&cwd, prefix)
+            .into_iter()
+            .filter(|(el, _)| el.starts_with(partial))
+            .map(|(el, more)| Pair {
+                replacement: if more {
+                    format!("{el}, ")
+                } else {
+                    format!("{el}){quote}")
+                },
+                display: el,
+            })
+            .collect();
+        Some((start, pairs))
+    }
+}
+
+async fn refresh_children(db: &Client, cache: &ChildCache, path: Vec<String>) {
+    if let Ok(items) = crate::util::list_dirs(db, &path).await {
+        cache.lock().unwrap().insert(path, (Instant::now(), items));
+    }
 }
 
 impl Helper for ReplHelper {}
@@ -33,7 +206,12 @@ impl Completer for ReplHelper {
         _pos: usize,
         _ctx: &Context<'_>,
     ) -> rustyline::Result<(usize, Vec<Pair>)> {
-        let commands = ["help", "exit", "quit", "pwd", "cd", "ls", "scan"];
+        if let Some(found) = self.complete_key(line) {
+            return Ok(found);
+        }
+        let commands = crate::help::COMMANDS
+            .iter()
+            .flat_map(|c| c.names.iter().copied());
         let parts = shell_words::split(line).unwrap_or_else(|_| vec![line.to_string()]);
         let is_space_term = line.ends_with(' ');
 
@@ -42,7 +220,7 @@ impl Completer for ReplHelper {
             let start = 0;
             let prefix = line.trim_start();
             let mut out = vec![];
-            for &cmd in &commands {
+            for cmd in commands {
                 if cmd.starts_with(prefix) {
                     out.push(Pair {
                         display: cmd.to_string(),
@@ -53,9 +231,60 @@ impl Completer for ReplHelper {
             return Ok((start, out));
         }
 
-        // Path completion for cd/ls/scan first argument
+        // Command names again for `help <command>`
         let cmd = &parts[0];
-        if ["cd", "ls", "scan"].contains(&cmd.as_str()) {
+        if cmd == "help" && parts.len() == 1 + usize::from(!is_space_term) {
+            let token = if is_space_term { "" } else { parts[1].as_str() };
+            let pairs = commands
+                .filter(|c| c.starts_with(token))
+                .map(|c| Pair {
+                    display: c.to_string(),
+                    replacement: c.to_string(),
+                })
+                .collect();
+            return Ok((line.len() - token.len(), pairs));
+        }
+
+        // Flag completion when the current token starts with '-'
+
+        if !is_space_term {
+            let token = parts.last().map(|s| s.as_str()).unwrap_or("");
+            if token.starts_with('-') {
+                let pairs = command_flags(cmd)
+                    .into_iter()
+                    .filter(|f| f.starts_with(token))
+                    .map(|f| Pair {
+                        display: f.to_string(),
+                        replacement: f.to_string(),
+                    })
+                    .collect();
+                let start = line
+                    .rfind(|c| [' ', '\t'].contains(&c))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                return Ok((start, pairs));
+            }
+        }
+
+        // Path completion for cd/ls/scan first argument
+        if [
+            "cd",
+            "ls",
+            "scan",
+            "export",
+            "export-tree",
+            "cp",
+            "diff",
+            "watch-dir",
+            "mkdir",
+            "rmdir",
+            "rm",
+            "mv",
+            "exists",
+            "groupby",
+        ]
+        .contains(&cmd.as_str())
+        {
             // Determine current (possibly partial) token
             let token = if is_space_term {
                 ""
@@ -78,22 +307,7 @@ impl Completer for ReplHelper {
                 (parent, needle)
             };
 
-            let db = self.db.clone();
-            let parent_for_run = parent.clone();
-            let fut = async move {
-                db.run(|trx, _| {
-                    let parent = parent_for_run.clone();
-                    async move {
-                        let dl = DirectoryLayer::default();
-                        let items = dl.list(&trx, &parent).await?;
-                        Ok::<_, foundationdb::FdbBindingError>(items)
-                    }
-                })
-                .await
-            };
-            let items: Vec<String> =
-                task::block_in_place(|| tokio::runtime::Handle::current().block_on(fut))
-                    .unwrap_or_default();
+            let items = self.cached_children(&parent);
             let mut pairs = vec![];
             let add_slash = token.ends_with('/');
             for name in items {
@@ -132,27 +346,136 @@ impl Completer for ReplHelper {
     }
 }
 
-pub async fn run_repl(db: foundationdb::Database) -> Result<()> {
+// Flags accepted by each REPL command, offered by tab completion.
+fn command_flags(cmd: &str) -> Vec<&'static str> {
+    const READ: &[&str] = &["--snapshot", "--no-snapshot"];
+    const FORMAT: &[&str] = &[
+        "--pretty",
+        "--hex",
+        "--hex-keys",
+        "--int-hex",
+        "--b64",
+        "--vs-hex",
+        "--precision",
+        "--bare-floats",
+        "--flat-colors",
+        "--absolute-keys",
+        "--deep-decode",
+        "--value-as",
+        "--full",
+        "--max-bytes",
+        "--max-key-bytes",
+        "--max-value-bytes",
+    ];
+    match cmd {
+        "ls" => [
+            &[
+                "--counts",
+                "--sort",
+                "--by-prefix",
+                "--long",
+                "--keys",
+                "--format",
+                "--layer",
+                "--parallel",
+            ],
+            READ,
+            FORMAT,
+        ]
+        .concat(),
+        "scan" | "dump" | "subspace" | "keys" => [
+            &[
+                "--limit",
+                "--all",
+                "--tail",
+                "--after",
+                "--begin-sel",
+                "--end-sel",
+                "--prefix",
+                "--prefix-tuple",
+                "--raw",
+                "--fields",
+                "--schema",
+                "--verify-roundtrip",
+                "--format",
+                "--layer",
+            ],
+            READ,
+            FORMAT,
+        ]
+        .concat(),
+        "rawscan" => [&["--limit", "--all", "--system"], READ, FORMAT].concat(),
+        "groupby" => [&["--limit", "--all"], FORMAT].concat(),
+        "get" | "cat" => FORMAT.to_vec(),
+        "export" => vec!["--output", "--parallel"],
+        "export-tree" => vec!["--output", "--depth"],
+        "import" => vec!["--skip-existing", "--dry-run", "--yes"],
+        "load" => vec!["--clear-first", "--dry-run", "--yes"],
+        "cp" => vec!["--layer", "--dry-run", "--yes"],
+        "diff" => [&["--verbose"], FORMAT].concat(),
+        "mv" | "rename" => vec!["--dry-run", "--yes"],
+        "find" => vec!["--max-depth"],
+        "grep" => [&["--limit", "--all", "--ignore-case"], FORMAT].concat(),
+        "cd" => vec!["--layer", "--create", "--yes"],
+        "pwd" => vec!["--layers"],
+        "cluster-status" => vec!["--raw"],
+        "mkdir" => vec!["--layer"],
+        "rmdir" | "rm" | "clear-prefix" => vec!["--dry-run", "--yes"],
+        _ => vec![],
+    }
+}
+
+// `history` is None with --no-history: nothing is loaded or saved.
+pub async fn run_repl(db: Client, history: Option<PathBuf>, timing: bool) -> Result<()> {
     let db = Arc::new(db);
-    let mut rl: Editor<ReplHelper, _> = Editor::new()?;
-    let cwd_shared: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    // Emacs bindings give Ctrl-R reverse incremental history search.
+    let config = Config::builder()
+        .history_ignore_dups(true)?
+        .history_ignore_space(true)
+        .max_history_size(HISTORY_SIZE)?
+        .edit_mode(EditMode::Emacs)
+        .build();
+    let mut rl: Editor<ReplHelper, _> = Editor::with_config(config)?;
+    let mut session = Session::new(db.clone(), Output::stdout(), true, timing);
+    session.load_settings();
+    // --timing on the command line wins over a saved `timing off`.
+    if timing {
+        session.settings.timing = true;
+    }
     let helper: ReplHelper = ReplHelper {
-        db: db.clone(),
-        cwd: cwd_shared.clone(),
+        db,
+        cwd: session.cwd_shared.clone(),
+        children: session.children.clone(),
+        elements: Arc::new(Mutex::new(HashMap::new())),
     };
     rl.set_helper(Some(helper));
 
-    // History file path: ~/.fdbdir_history
-    let hist_path: PathBuf = dirs::home_dir()
-        .map(|p| p.join(".fdbdir_history"))
-        .unwrap_or_else(|| PathBuf::from(".fdbdir_history"));
-    let _ = rl.load_history(&hist_path);
-    let mut cwd: Vec<String> = vec![];
+    if let Some(path) = &history {
+        let legacy = dirs::home_dir().map(|h| h.join(LEGACY_HISTORY_FILE));
+        match legacy.filter(|l| !path.exists() && l.exists()) {
+            Some(legacy) => {
+                let _ = rl.load_history(&legacy);
+            }
+            None => {
+                let _ = rl.load_history(path);
+            }
+        }
+    }
 
+    refresh_children(&session.db, &session.children, vec![]).await;
     println!("fdbdir interactive. Type 'help' for commands.\n");
 
     loop {
-        let prompt = format!("fdb:{}> ", display_path(&cwd).bold());
+        let pinned = match (&session.txn, session.read_version) {
+            (Some((_, v)), _) => format!(" [txn@{v}]"),
+            (None, Some(v)) => format!("@{v}"),
+            (None, None) => String::new(),
+        };
+        let prompt = format!(
+            "fdb:{}{}> ",
+            display_path(&session.cwd).bold(),
+            pinned.dimmed()
+        );
         let line = match rl.readline(&prompt) {
             Ok(line) => line,
             Err(ReadlineError::Interrupted) => {
@@ -168,116 +491,1384 @@ pub async fn run_repl(db: foundationdb::Database) -> Result<()> {
         }
         rl.add_history_entry(line)?;
 
+        // Ctrl-C drops the running command, and with it any in-flight transaction;
+        // whatever it already printed stays on screen.
+        let res = tokio::select! {
+            biased;
+            res = session.execute(line) => Some(res),
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        match res {
+            Some(Ok(Flow::Continue)) => {}
+            Some(Ok(Flow::Exit)) => break,
+            Some(Err(e)) => eprintln!("{} {e:#}", "error:".red().bold()),
+            None => {
+                session.end_paging()?;
+                session.out.flush()?;
+                eprintln!("{}", "^C (interrupted)".dimmed());
+            }
+        }
+    }
+    // Save history on exit
+    if let Some(path) = &history {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = rl.save_history(path);
+    }
+    Ok(())
+}
+
+// Runs REPL commands from `input`, one per line, without prompts or colors.
+// Blank lines and lines starting with '#' are skipped. Stops at the first
+// failing command unless `keep_going`, which still fails at the end.
+pub async fn run_script<R: BufRead>(
+    db: Client,
+    input: R,
+    out: Output,
+    keep_going: bool,
+    timing: bool,
+) -> Result<()> {
+    let mut session = Session::new(Arc::new(db), out, false, timing);
+    let mut failed = 0usize;
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match session.execute(line).await {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Exit) => break,
+            Err(e) => {
+                let e = e.context(format!("line {}: {line}", idx + 1));
+                if !keep_going {
+                    session.out.flush()?;
+                    return Err(e);
+                }
+                eprintln!("error: {e:#}");
+                failed += 1;
+            }
+        }
+    }
+    session.out.flush()?;
+    if failed > 0 {
+        return Err(anyhow!("{failed} command(s) failed"));
+    }
+    Ok(())
+}
+
+// ls/scan/get read through the open transaction, if any. A free function so the
+// borrow of `txn` stays disjoint from `&mut self.out`.
+fn source<'a>(txn: &'a Option<(Transaction, i64)>, db: &'a Client) -> Source<'a> {
+    match txn {
+        Some((trx, _)) => Source::Trx(db, trx),
+        None => Source::Db(db),
+    }
+}
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+// Options changed with `set` and listed by `show`. The interactive REPL loads
+// them from the [settings] table of the config file and saves each change back.
+#[derive(Clone, Debug)]
+struct Settings {
+    color: bool,
+    // Ask before destructive commands; off acts like --yes.
+    confirm: bool,
+    timing: bool,
+    // Rows shown by scan/keys/subspace/rawscan/grep without --limit or --all.
+    limit: usize,
+    max_bytes: usize,
+    // Values colored by tuple element type, or all green.
+    typed_colors: bool,
+    // Page long listings (see PAGED) through $PAGER.
+    pager: bool,
+    // A name from util::STREAMING_MODES, used by range reads.
+    streaming: String,
+    // Estimated size over which unlimited scans and exports are refused; None is off.
+    max_scan_bytes: Option<u64>,
+}
+
+const SETTINGS: &[&str] = &[
+    "color",
+    "value-colors",
+    "confirm",
+    "timing",
+    "pager",
+    "limit",
+    "max-bytes",
+    "streaming",
+    "max-scan-bytes",
+];
+
+fn on_off(value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(anyhow!("expected on or off, got '{value}'")),
+    }
+}
+
+impl Settings {
+    fn new(color: bool, timing: bool) -> Self {
+        Self {
+            color,
+            confirm: true,
+            timing,
+            limit: 50,
+            max_bytes: crate::format::DEFAULT_MAX_BYTES,
+            typed_colors: true,
+            pager: false,
+            streaming: "auto".to_string(),
+            max_scan_bytes: None,
+        }
+    }
+
+    fn get(&self, key: &str) -> String {
+        let flag = |on: bool| if on { "on" } else { "off" }.to_string();
+        match key {
+            "color" => flag(self.color),
+            "confirm" => flag(self.confirm),
+            "timing" => flag(self.timing),
+            "pager" => flag(self.pager),
+            "limit" => self.limit.to_string(),
+            "max-bytes" => self.max_bytes.to_string(),
+            "value-colors" if self.typed_colors => "typed".to_string(),
+            "value-colors" => "flat".to_string(),
+            "max-scan-bytes" => self
+                .max_scan_bytes
+                .map_or("off".to_string(), |n| n.to_string()),
+            _ => self.streaming.clone(),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| anyhow!("{key} needs a number, got '{value}'"))
+        };
+        match key {
+            "color" => self.color = on_off(value)?,
+            "confirm" => self.confirm = on_off(value)?,
+            "timing" => self.timing = on_off(value)?,
+            "pager" => self.pager = on_off(value)?,
+            // Stored limits come through here too, so a 0 saved by an older
+            // version is reported at startup instead of failing every scan.
+            "limit" => {
+                self.limit = match number()? {
+                    0 => return Err(anyhow!("limit must be at least 1, got 0")),
+                    n => n,
+                }
+            }
+            "max-bytes" => self.max_bytes = number()?,
+            "value-colors" => {
+                self.typed_colors = match value {
+                    "typed" => true,
+                    "flat" => false,
+                    _ => return Err(anyhow!("value-colors is typed or flat, got '{value}'")),
+                }
+            }
+            "max-scan-bytes" => {
+                self.max_scan_bytes = match value {
+                    "off" => None,
+                    v => Some(crate::parse::parse_byte_size(v)?),
+                }
+            }
+            "streaming" => {
+                crate::parse::parse_streaming_mode(value)?;
+                self.streaming = value.to_string();
+            }
+            _ => {
+                return Err(anyhow!(
+                    "unknown setting '{key}' (have: {})",
+                    SETTINGS.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn streaming_mode(&self) -> Option<foundationdb::options::StreamingMode> {
+        // Validated by `set`.
+        crate::parse::parse_streaming_mode(&self.streaming).unwrap_or_default()
+    }
+}
+
+// Stored under snake_case names, like the profile keys.
+fn config_key(key: &str) -> String {
+    key.replace('-', "_")
+}
+
+// State carried from one command to the next, shared by the REPL and script mode.
+struct Session {
+    db: Arc<Client>,
+    out: Output,
+    cwd: Vec<String>,
+    // Target of `cd -`.
+    prev_cwd: Vec<String>,
+    read_version: Option<i64>,
+    // Where `more` resumes: the last scan's directory and options, with `after` at its cursor.
+    next_scan: Option<(Vec<String>, ScanOptions, ReadOptions, FormatOptions)>,
+    settings: Settings,
+    // False in script mode, where stdin holds commands and nobody can answer a prompt.
+    interactive: bool,
+    cwd_shared: Arc<Mutex<Vec<String>>>,
+    children: ChildCache,
+    // Read transaction held between `begin` and `end`, with its read version.
+    txn: Option<(Transaction, i64)>,
+}
+
+impl Session {
+    fn new(db: Arc<Client>, out: Output, interactive: bool, timing: bool) -> Self {
+        Self {
+            db,
+            cwd: vec![],
+            prev_cwd: vec![],
+            read_version: None,
+            next_scan: None,
+            settings: Settings::new(out.color(), timing),
+            out,
+            interactive,
+            cwd_shared: Arc::new(Mutex::new(vec![])),
+            children: Arc::new(Mutex::new(HashMap::new())),
+            txn: None,
+        }
+    }
+
+    // Confirmation for destructive commands; 'set confirm off' acts like --yes.
+    fn approve(&self, cmd: &str, prompt: &str, yes: bool) -> Result<bool> {
+        crate::util::approve(cmd, prompt, yes || !self.settings.confirm, self.interactive)
+    }
+
+    // Applies a setting to the session, including the parts that live outside it.
+    fn apply_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        self.settings.set(key, value)?;
+        self.out.set_color(self.settings.color);
+        Ok(())
+    }
+
+    // Settings saved by earlier sessions. A bad entry is reported and skipped
+    // rather than keeping the REPL from starting.
+    fn load_settings(&mut self) {
+        let tables = match crate::config::load() {
+            Ok(tables) => tables,
+            Err(e) => {
+                eprintln!("{} {e:#}", "warning:".yellow().bold());
+                return;
+            }
+        };
+        for (key, value) in tables.get("settings").into_iter().flatten() {
+            let value = match value {
+                crate::config::Value::Bool(b) => if *b { "on" } else { "off" }.to_string(),
+                crate::config::Value::Int(n) => n.to_string(),
+                crate::config::Value::Str(s) => s.clone(),
+            };
+            if let Err(e) = self.apply_setting(&key.replace('_', "-"), &value) {
+                eprintln!("{} [settings] {key}: {e}", "warning:".yellow().bold());
+            }
+        }
+    }
+
+    fn format_defaults(&self) -> FormatOptions {
+        FormatOptions {
+            max_key_bytes: self.settings.max_bytes,
+            max_value_bytes: self.settings.max_bytes,
+            flat_colors: !self.settings.typed_colors,
+            width: crate::util::terminal_width(),
+            ..FormatOptions::default()
+        }
+    }
+
+    // The summary (and with timing on, the timing) of a scan; returns where
+    // `more` continues from.
+    fn report_scan(&self, scanned: Scanned) -> Option<Vec<u8>> {
+        crate::format::print_scan_summary(&scanned);
+        if self.settings.timing {
+            crate::format::print_read_timing("scan", scanned.timing);
+        }
+        scanned.cursor
+    }
+
+    fn set_cwd(&mut self, path: Vec<String>) {
+        self.prev_cwd = std::mem::replace(&mut self.cwd, path);
+        *self.cwd_shared.lock().unwrap() = self.cwd.clone();
+    }
+
+    async fn execute(&mut self, line: &str) -> Result<Flow> {
+        let started = Instant::now();
+        let mut line = line.to_string();
+        if self.interactive {
+            if let Ok(mut parts) = shell_words::split(&line) {
+                let asked = take_flag(&mut parts, &["--pager"]);
+                let cmd = parts.first().map(String::as_str).unwrap_or_default();
+                if asked || (self.settings.pager && PAGED.contains(&cmd)) {
+                    line = shell_words::join(&parts);
+                    // Falls back to plain stdout when no pager can be started.
+                    if let Some(pager) = Output::pager(self.out.color()) {
+                        self.out = pager;
+                    }
+                }
+            }
+        }
+        let res = self.dispatch(&line).await;
+        self.end_paging()?;
+        let cmd = line.split_whitespace().next().unwrap_or_default();
+        if self.settings.timing {
+            crate::format::print_timing(cmd, started.elapsed(), &[]);
+        }
+        res
+    }
+
+    // Back to stdout after a paged command, once the user quits the pager.
+    fn end_paging(&mut self) -> Result<()> {
+        if self.out.is_pager() {
+            let mut stdout = Output::stdout();
+            stdout.set_color(self.out.color());
+            std::mem::replace(&mut self.out, stdout).finish()?;
+        }
+        Ok(())
+    }
+
+    async fn dispatch(&mut self, line: &str) -> Result<Flow> {
+        let db = self.db.clone();
+        let cwd = self.cwd.clone();
+        let read_version = self.read_version;
         let mut parts = shell_words::split(line).unwrap_or_else(|_| vec![line.to_string()]);
         let cmd = parts.remove(0);
         match cmd.as_str() {
-            "help" => print_help(),
-            "quit" | "exit" => break,
-            "pwd" => println!("{}", display_path(&cwd)),
+            "help" => match parts.first() {
+                Some(name) => crate::help::print_command(name),
+                None => crate::help::print_overview(),
+            },
+            "quit" | "exit" => return Ok(Flow::Exit),
+            "pwd" => match &parts[..] {
+                [] => writeln!(self.out, "{}", display_path(&cwd))?,
+                [flag] if flag == "--layers" => {
+                    crate::util::print_layers(&db, &mut self.out, &cwd).await?
+                }
+                _ => return Err(anyhow!("Usage: pwd [--layers]")),
+            },
             "cd" => {
-                let target = parts.first().map(|s| s.as_str()).unwrap_or("/");
-                let new_path = if target == "/" {
-                    vec![]
-                } else if target == ".." {
-                    let mut p = cwd.clone();
-                    p.pop();
-                    p
-                } else if target.starts_with('/') {
-                    parse_path(target)
-                } else {
-                    let mut p = cwd.clone();
-                    p.extend(parse_path(target));
-                    p
-                };
-
-                // Validate by attempting to open
-                let ok = match db
-                    .run(|trx, _| {
-                        let path = new_path.clone();
-                        async move {
-                            let dl = DirectoryLayer::default();
-                            let exists = dl.exists(&trx, &path).await?;
-                            Ok(exists)
-                        }
-                    })
-                    .await
-                {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("{} {:?}", "error:".red().bold(), e);
-                        false
+                let create = take_flag(&mut parts, &["--create"]);
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let layer = take_value(&mut parts, "--layer")?
+                    .map(|l| crate::parse::parse_bytes_literal(&l))
+                    .transpose()?;
+                let target = match &parts[..] {
+                    [] => "/",
+                    [t] => t.as_str(),
+                    _ => {
+                        return Err(anyhow!(
+                            "Usage: cd [path] [--layer <bytes>] [--create [--yes]]"
+                        ))
                     }
                 };
-                if ok {
-                    cwd = new_path;
-                    *cwd_shared.lock().unwrap() = cwd.clone();
+                let new_path = if target == "-" {
+                    self.prev_cwd.clone()
                 } else {
-                    println!("No such directory: {}", display_path(&new_path));
+                    resolve_path(&cwd, target)
+                };
+
+                if !crate::util::dir_exists(&db, &new_path).await? {
+                    if !create {
+                        return Err(anyhow!("No such directory: {}", display_path(&new_path)));
+                    }
+                    let prompt = format!("Create {}?", display_path(&new_path));
+                    if !self.approve("cd --create", &prompt, yes)? {
+                        return Ok(Flow::Continue);
+                    }
+                    crate::util::make_dir(&db, &new_path, layer.as_deref()).await?;
+                    self.children.lock().unwrap().clear();
+                } else if let Some(layer) = &layer {
+                    crate::util::check_layer(&db, &new_path, layer).await?;
+                }
+                if target == "-" {
+                    writeln!(self.out, "{}", display_path(&new_path))?;
+                }
+                self.set_cwd(new_path);
+                if self.interactive {
+                    refresh_children(&db, &self.children, self.cwd.clone()).await;
                 }
             }
             "ls" => {
-                let target = parts.first().map(|s| s.as_str());
-                let path = match target {
-                    None => cwd.clone(),
-                    Some(".") => cwd.clone(),
-                    Some("..") => {
-                        let mut t = cwd.clone();
-                        t.pop();
-                        t
+                let mut ls = LsOptions::default();
+                let mut snapshot = true;
+                let mut fmt = self.format_defaults();
+                let mut target = None;
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
                     }
-                    Some(p) if p.starts_with('/') => parse_path(p),
-                    Some(p) => {
-                        let mut t = cwd.clone();
-                        t.extend(parse_path(p));
-                        t
+                    match tok.as_str() {
+                        "--snapshot" => snapshot = true,
+                        "--no-snapshot" => snapshot = false,
+                        "--counts" => ls.counts = true,
+                        "--sort" => {
+                            ls.sort = Some(match it.next().map(String::as_str) {
+                                Some("name") => LsSort::Name,
+                                Some("name-desc") => LsSort::NameDesc,
+                                Some("size") => LsSort::Size,
+                                Some("prefix") => LsSort::Prefix,
+                                _ => {
+                                    return Err(anyhow!(
+                                        "--sort needs name, name-desc, size, or prefix"
+                                    ))
+                                }
+                            })
+                        }
+                        "--by-prefix" => ls.sort = Some(LsSort::Prefix),
+                        "-l" | "--long" => ls.long = true,
+                        "--keys" => {
+                            ls.keys = it
+                                .next()
+                                .and_then(|n| n.parse().ok())
+                                .ok_or_else(|| anyhow!("--keys needs a number"))?
+                        }
+                        "--layer" => {
+                            let layer = it
+                                .next()
+                                .ok_or_else(|| anyhow!("--layer requires a value"))?;
+                            ls.layer = Some(crate::parse::parse_bytes_literal(layer)?);
+                        }
+                        "--parallel" => {
+                            ls.parallel = it
+                                .next()
+                                .and_then(|n| n.parse().ok())
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| anyhow!("--parallel needs a positive number"))?
+                        }
+                        "--format" => {
+                            ls.format = match it.next().map(String::as_str) {
+                                Some("text") => OutputFormat::Text,
+                                Some("json") => OutputFormat::Json,
+                                _ => return Err(anyhow!("--format needs text or json")),
+                            }
+                        }
+                        p if target.is_none() => target = Some(p),
+                        _ => {}
                     }
+                }
+                let path = match target {
+                    None => cwd.clone().into(),
+                    Some(p) => resolve_pattern(&cwd, p),
                 };
 
-                if let Err(e) = crate::util::ls_path(&db, path).await {
-                    eprintln!("{} {:?}", "error:".red().bold(), e);
+                let read = ReadOptions {
+                    snapshot,
+                    read_version,
+                };
+                let listings = crate::util::ls_path(
+                    source(&self.txn, &db),
+                    &mut self.out,
+                    path,
+                    ls,
+                    read,
+                    fmt,
+                )
+                .await?;
+                if self.settings.timing {
+                    for listing in &listings {
+                        crate::format::print_read_timing("ls", listing.timing);
+                    }
                 }
             }
-            "scan" | "dump" => {
-                // Parse optional [limit] and/or [prefix]
-                let mut limit: usize = 50;
-                let mut prefix: Option<Vec<u8>> = None;
+            "scan" | "dump" | "subspace" | "keys" => {
+                // Parse optional [limit] and/or [prefix]; subspace requires a tuple prefix.
+                let mut scan = ScanOptions {
+                    limit: Some(self.settings.limit),
+                    subspace: cmd == "subspace",
+                    keys_only: cmd == "keys",
+                    streaming: self.settings.streaming_mode(),
+                    max_scan_bytes: self.settings.max_scan_bytes,
+                    ..ScanOptions::default()
+                };
                 let mut raw = false;
-                for tok in parts.iter() {
+                let mut snapshot = true;
+                let mut prefix = None;
+                let mut prefix_tuple = false;
+                let mut fmt = self.format_defaults();
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
+                    }
+                    if tok == "--prefix-tuple" {
+                        prefix_tuple = true;
+                        continue;
+                    }
                     if tok == "--raw" || tok == "-r" || tok == "raw" {
                         raw = true;
                         continue;
                     }
-                    if let Ok(n) = tok.parse::<usize>() {
-                        limit = n;
+                    if tok == "--snapshot" {
+                        snapshot = true;
+                        continue;
+                    }
+                    if tok == "--no-snapshot" {
+                        snapshot = false;
                         continue;
                     }
-                    if prefix.is_none() {
-                        if let Ok(b) = crate::util::parse_bytes_literal(tok) {
-                            prefix = Some(b);
+                    if tok == "--all" || tok == "all" {
+                        scan.limit = None;
+                        continue;
+                    }
+                    if tok == "--limit" || tok == "-n" {
+                        let n = it.next().and_then(|n| n.parse::<usize>().ok());
+                        scan.limit = Some(n.ok_or_else(|| anyhow!("--limit requires a number"))?);
+                        continue;
+                    }
+                    if tok == "--verify-roundtrip" {
+                        scan.verify_roundtrip = true;
+                        continue;
+                    }
+                    if tok == "--layer" {
+                        let layer = it
+                            .next()
+                            .ok_or_else(|| anyhow!("--layer requires a value"))?;
+                        scan.layer = Some(crate::parse::parse_bytes_literal(layer)?);
+                        continue;
+                    }
+                    if tok == "--format" {
+                        scan.format = match it.next().map(String::as_str) {
+                            Some("list") => ScanFormat::List,
+                            Some("table") => ScanFormat::Table,
+                            _ => return Err(anyhow!("--format needs list or table")),
+                        };
+                        continue;
+                    }
+                    if tok == "--fields" {
+                        let fields = it
+                            .next()
+                            .ok_or_else(|| anyhow!("--fields requires indices"))?;
+                        scan.fields = Some(crate::parse::parse_fields(fields)?);
+                        continue;
+                    }
+                    if tok == "--schema" {
+                        let schema = it
+                            .next()
+                            .ok_or_else(|| anyhow!("--schema requires field names"))?;
+                        scan.schema = Some(crate::parse::parse_schema(schema)?);
+                        continue;
+                    }
+                    if tok == "--tail" {
+                        let n = it
+                            .next()
+                            .and_then(|n| n.parse().ok())
+                            .filter(|&n| n > 0)
+                            .ok_or_else(|| anyhow!("--tail needs a positive number"))?;
+                        scan.tail = Some(n);
+                        continue;
+                    }
+                    if tok == "--begin-sel" || tok == "--end-sel" {
+                        let sel = it
+                            .next()
+                            .ok_or_else(|| anyhow!("{tok} requires a selector"))?;
+                        let sel = Some(crate::parse::parse_selector(sel)?);
+                        if tok == "--begin-sel" {
+                            scan.begin_sel = sel;
+                        } else {
+                            scan.end_sel = sel;
                         }
+                        continue;
+                    }
+                    if tok == "--after" {
+                        let key = it.next().ok_or_else(|| anyhow!("--after requires a key"))?;
+                        scan.after = Some(
+                            crate::parse::parse_key_literal(key).context("invalid --after key")?,
+                        );
+                        continue;
+                    }
+                    if let Ok(n) = tok.parse::<usize>() {
+                        scan.limit = Some(n);
+                        continue;
+                    }
+                    let tok = if tok == "--prefix" || tok == "-p" {
+                        it.next()
+                            .ok_or_else(|| anyhow!("--prefix requires a value"))?
+                    } else {
+                        tok
+                    };
+                    prefix = prefix.or(Some(tok));
+                }
+                // Parsed after the loop, since --prefix-tuple may follow the prefix.
+                scan.prefix = match prefix {
+                    None if scan.subspace => {
+                        return Err(anyhow!("Usage: subspace <tuple> [limit]"));
                     }
+                    None => None,
+                    Some(tok) if scan.subspace => {
+                        let items = crate::parse::parse_tuple_literal(tok)
+                            .context("invalid subspace tuple")?;
+                        Some(foundationdb::tuple::pack(&items))
+                    }
+                    Some(tok) if prefix_tuple => Some(
+                        crate::parse::parse_prefix_element(tok)
+                            .context("invalid prefix element")?,
+                    ),
+                    Some(tok) => {
+                        Some(crate::parse::parse_key_literal(tok).context("invalid prefix")?)
+                    }
+                };
+                if scan.after.is_some() && scan.begin_sel.is_some() {
+                    return Err(anyhow!(
+                        "--after and --begin-sel both set the start; use one"
+                    ));
+                }
+                if prefix_tuple && scan.subspace {
+                    return Err(anyhow!(
+                        "subspace already takes a tuple; drop --prefix-tuple"
+                    ));
+                }
+                if prefix_tuple && scan.prefix.is_none() {
+                    return Err(anyhow!("--prefix-tuple needs a prefix"));
                 }
 
-                if let Err(e) = crate::util::scan_path(&db, cwd.clone(), limit, prefix, raw).await {
-                    eprintln!("{} {:?}", "error:".red().bold(), e);
+                fmt.raw_keys = raw;
+                let read = ReadOptions {
+                    snapshot,
+                    read_version,
+                };
+                self.next_scan = None;
+                let scanned = crate::util::scan_path(
+                    source(&self.txn, &db),
+                    &mut self.out,
+                    cwd.clone(),
+                    scan.clone(),
+                    read,
+                    fmt,
+                )
+                .await?;
+                let cursor = self.report_scan(scanned);
+                self.next_scan = cursor.map(|after| {
+                    let scan = ScanOptions {
+                        after: Some(after),
+                        ..scan
+                    };
+                    (cwd.clone(), scan, read, fmt)
+                });
+            }
+            "rawscan" => {
+                let mut raw = RawScanOptions {
+                    begin: Vec::new(),
+                    end: None,
+                    limit: Some(self.settings.limit),
+                    system: false,
+                    streaming: self.settings.streaming_mode(),
+                };
+                let mut snapshot = true;
+                let mut fmt = self.format_defaults();
+                let mut keys = Vec::new();
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
+                    }
+                    match tok.as_str() {
+                        "--snapshot" => snapshot = true,
+                        "--no-snapshot" => snapshot = false,
+                        "--system" => raw.system = true,
+                        "--all" => raw.limit = None,
+                        "--limit" | "-n" => {
+                            let n = it.next().and_then(|n| n.parse::<usize>().ok());
+                            raw.limit =
+                                Some(n.ok_or_else(|| anyhow!("--limit requires a number"))?);
+                        }
+                        k => keys.push(k),
+                    }
+                }
+                let (begin, end) = match keys.as_slice() {
+                    [begin] => (begin, None),
+                    [begin, end] => (begin, Some(end)),
+                    _ => return Err(anyhow!("Usage: rawscan <begin> [end]")),
+                };
+                raw.begin = crate::parse::parse_key_literal(begin).context("invalid begin key")?;
+                raw.end = end
+                    .map(|e| crate::parse::parse_key_literal(e).context("invalid end key"))
+                    .transpose()?;
+                let read = ReadOptions {
+                    snapshot,
+                    read_version,
+                };
+                crate::util::raw_scan(&db, &mut self.out, raw, read, fmt).await?;
+            }
+            "more" => {
+                let Some((path, scan, read, fmt)) = self.next_scan.clone() else {
+                    writeln!(self.out, "Nothing more to scan.")?;
+                    return Ok(Flow::Continue);
+                };
+                // On error the cursor is kept so `more` can be retried.
+                let scanned = crate::util::scan_path(
+                    source(&self.txn, &db),
+                    &mut self.out,
+                    path.clone(),
+                    scan.clone(),
+                    read,
+                    fmt,
+                )
+                .await?;
+                let cursor = self.report_scan(scanned);
+                self.next_scan = cursor.map(|after| {
+                    let scan = ScanOptions {
+                        after: Some(after),
+                        ..scan
+                    };
+                    (path, scan, read, fmt)
+                });
+            }
+            "version" => {
+                let v = match &self.txn {
+                    Some((_, v)) => *v,
+                    None => crate::util::read_version(&db).await?,
+                };
+                writeln!(self.out, "{v}")?;
+            }
+            "env" | "status" => crate::status::print_env(&db, &mut self.out).await?,
+            "cluster-status" => {
+                let raw = match &parts[..] {
+                    [] => false,
+                    [flag] if flag == "--raw" => true,
+                    _ => return Err(anyhow!("Usage: cluster-status [--raw]")),
+                };
+                crate::status::print_cluster_status(&db, &mut self.out, raw).await?;
+            }
+            "workers" => {
+                if !parts.is_empty() {
+                    return Err(anyhow!("Usage: workers"));
+                }
+                crate::status::print_workers(&db, &mut self.out).await?;
+            }
+            "begin" => {
+                if let Some((_, v)) = &self.txn {
+                    return Err(anyhow!(
+                        "Transaction already open at version {v}; 'end' it first"
+                    ));
+                }
+                let trx = db.create_trx().map_err(|e| anyhow!(describe_error(e)))?;
+                ReadOptions {
+                    snapshot: true,
+                    read_version,
+                }
+                .apply(&db, &trx)
+                .map_err(|e| anyhow!(describe_error(e)))?;
+                let v = trx
+                    .get_read_version()
+                    .await
+                    .map_err(|e| anyhow!(describe_error(e)))?;
+                self.txn = Some((trx, v));
+                writeln!(
+                    self.out,
+                    "Transaction started at version {v} (reads stay valid for about 5 seconds)"
+                )?;
+            }
+            "end" | "rollback" => {
+                if self.txn.take().is_none() {
+                    return Err(anyhow!("No open transaction"));
+                }
+            }
+            "setversion" => match parts.first().map(|s| s.parse::<i64>()) {
+                Some(Ok(v)) => self.read_version = Some(v),
+                _ => return Err(anyhow!("Usage: setversion <version>")),
+            },
+            "clearversion" => self.read_version = None,
+            "export" => {
+                let mut target = None;
+                let mut file = None;
+                let mut parallel = 1;
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    match tok.as_str() {
+                        "-o" | "--output" => file = it.next().cloned(),
+                        "--parallel" => {
+                            parallel = it
+                                .next()
+                                .and_then(|n| n.parse().ok())
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| anyhow!("--parallel needs a positive number"))?
+                        }
+                        p if target.is_none() => target = Some(p),
+                        _ => {}
+                    }
+                }
+                let path = target.map(|p| resolve_path(&cwd, p)).unwrap_or(cwd.clone());
+                match file {
+                    Some(file) => {
+                        let mut f = std::io::BufWriter::new(std::fs::File::create(&file)?);
+                        let n = crate::dump::export_path(
+                            &db,
+                            path,
+                            &mut f,
+                            parallel,
+                            self.settings.max_scan_bytes,
+                            std::io::stderr().is_terminal(),
+                        )
+                        .await?;
+                        writeln!(self.out, "exported {n} keys to {file}")?;
+                    }
+                    None => {
+                        crate::dump::export_path(
+                            &db,
+                            path,
+                            &mut self.out,
+                            parallel,
+                            self.settings.max_scan_bytes,
+                            false,
+                        )
+                        .await?;
+                    }
+                }
+            }
+            "export-tree" => {
+                let mut target = None;
+                let mut file = None;
+                let mut depth = None;
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    match tok.as_str() {
+                        "-o" | "--output" => file = it.next().cloned(),
+                        "--depth" => {
+                            depth = Some(
+                                it.next()
+                                    .and_then(|n| n.parse().ok())
+                                    .ok_or_else(|| anyhow!("--depth needs a number"))?,
+                            )
+                        }
+                        p if target.is_none() => target = Some(p),
+                        _ => {}
+                    }
+                }
+                let path = target.map(|p| resolve_path(&cwd, p)).unwrap_or(cwd.clone());
+                match file {
+                    Some(file) => {
+                        let mut f = std::io::BufWriter::new(std::fs::File::create(&file)?);
+                        let n = crate::dump::export_tree(&db, &mut f, path, depth).await?;
+                        writeln!(self.out, "exported {n} directories to {file}")?;
+                    }
+                    None => {
+                        crate::dump::export_tree(&db, &mut self.out, path, depth).await?;
+                    }
+                }
+            }
+            "import" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let mode = if take_flag(&mut parts, &["--skip-existing"]) {
+                    crate::dump::ImportMode::SkipExisting
+                } else {
+                    crate::dump::ImportMode::Overwrite
+                };
+                let args: Vec<&str> = parts.iter().map(String::as_str).collect();
+                let Some(file) = args.first() else {
+                    return Err(anyhow!(
+                        "Usage: import <file> [path] [--skip-existing] [--dry-run] [--yes]"
+                    ));
+                };
+                let path = args
+                    .get(1)
+                    .map(|p| resolve_path(&cwd, p))
+                    .unwrap_or(cwd.clone());
+                let input = std::io::BufReader::new(std::fs::File::open(file)?);
+                // Skipping existing keys never destroys data; overwriting can.
+                if !dry_run && mode == crate::dump::ImportMode::Overwrite {
+                    let prompt = format!(
+                        "Import {file} into {}, overwriting existing keys?",
+                        display_path(&path)
+                    );
+                    if !self.approve(&cmd, &prompt, yes)? {
+                        return Ok(Flow::Continue);
+                    }
+                }
+                crate::dump::import_path(&db, &mut self.out, path, input, mode, dry_run).await?;
+            }
+            "load" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let clear_first = take_flag(&mut parts, &["--clear-first"]);
+                let (file, path) = match &parts[..] {
+                    [file] => (file, cwd.clone()),
+                    [file, path] => (file, resolve_path(&cwd, path)),
+                    _ => {
+                        return Err(anyhow!(
+                            "Usage: load <file> [path] [--clear-first] [--dry-run] [--yes]"
+                        ))
+                    }
+                };
+                let input = std::io::BufReader::new(std::fs::File::open(file)?);
+                if !dry_run {
+                    let prompt = if clear_first {
+                        format!("Clear {} and load {file} into it?", display_path(&path))
+                    } else {
+                        format!(
+                            "Load {file} into {}, overwriting existing keys?",
+                            display_path(&path)
+                        )
+                    };
+                    if !self.approve(&cmd, &prompt, yes)? {
+                        return Ok(Flow::Continue);
+                    }
                 }
+                crate::dump::load_path(&db, &mut self.out, path, input, clear_first, dry_run)
+                    .await?;
+            }
+            "cp" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let mut layer = None;
+                let mut args = vec![];
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    match tok.as_str() {
+                        "--layer" => layer = it.next(),
+                        a => args.push(a),
+                    }
+                }
+                let [src, dst] = args[..] else {
+                    return Err(anyhow!(
+                        "Usage: cp <src> <dst> [--layer <bytes>] [--dry-run] [--yes]"
+                    ));
+                };
+                let layer = layer
+                    .map(|l| crate::parse::parse_bytes_literal(l))
+                    .transpose()?;
+                let (src, dst) = (resolve_path(&cwd, src), resolve_path(&cwd, dst));
+                let prompt = format!(
+                    "Copy {} into {}, overwriting keys that exist there?",
+                    display_path(&src),
+                    display_path(&dst)
+                );
+                if !dry_run && !self.approve(&cmd, &prompt, yes)? {
+                    return Ok(Flow::Continue);
+                }
+                crate::dump::copy_path(&db, &mut self.out, src, dst, layer, dry_run).await?;
+            }
+            "diff" => {
+                let verbose = take_flag(&mut parts, &["--verbose", "-v"]);
+                let mut fmt = self.format_defaults();
+                let mut args = vec![];
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if !format_flag(tok, &mut it, &mut fmt)? {
+                        args.push(tok.as_str());
+                    }
+                }
+                let [a, b] = args[..] else {
+                    return Err(anyhow!("Usage: diff <a> <b> [--verbose]"));
+                };
+                let (a, b) = (resolve_path(&cwd, a), resolve_path(&cwd, b));
+                crate::dump::diff_paths(&db, &mut self.out, a, b, verbose, fmt).await?;
+            }
+            "mkdir" => {
+                let mut layer = None;
+                let mut args = vec![];
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    match tok.as_str() {
+                        "--layer" => layer = it.next(),
+                        a => args.push(a),
+                    }
+                }
+                let [target] = args[..] else {
+                    return Err(anyhow!("Usage: mkdir <path> [--layer <bytes>]"));
+                };
+                let layer = layer
+                    .map(|l| crate::parse::parse_bytes_literal(l))
+                    .transpose()?;
+                let path = resolve_path(&cwd, target);
+                crate::util::make_dir(&db, &path, layer.as_deref()).await?;
+                self.children.lock().unwrap().clear();
+            }
+            "rmdir" | "rm" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let [target] = &parts[..] else {
+                    return Err(anyhow!("Usage: {cmd} <path> [--dry-run] [--yes]"));
+                };
+                let path = resolve_path(&cwd, target);
+                if dry_run {
+                    crate::util::plan_remove(&db, &mut self.out, &path).await?;
+                    return Ok(Flow::Continue);
+                }
+                let prompt = format!("Remove {} and everything under it?", display_path(&path));
+                if !self.approve(&cmd, &prompt, yes)? {
+                    return Ok(Flow::Continue);
+                }
+                crate::util::remove_dir(&db, &path).await?;
+                self.children.lock().unwrap().clear();
+                // Don't leave the session inside a directory that no longer exists.
+                if cwd.starts_with(&path) {
+                    self.set_cwd(path[..path.len() - 1].to_vec());
+                }
+            }
+            "clear-prefix" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let [prefix] = &parts[..] else {
+                    return Err(anyhow!("Usage: clear-prefix <prefix> [--dry-run] [--yes]"));
+                };
+                let prefix = crate::parse::parse_key_literal(prefix).context("invalid prefix")?;
+                if dry_run {
+                    crate::util::plan_clear_prefix(&db, &mut self.out, &cwd, &prefix).await?;
+                    return Ok(Flow::Continue);
+                }
+                let prompt = format!(
+                    "Clear every key under {} in {}?",
+                    crate::format::key_literal(&prefix),
+                    display_path(&cwd)
+                );
+                if !self.approve(&cmd, &prompt, yes)? {
+                    return Ok(Flow::Continue);
+                }
+                crate::util::clear_prefix(&db, &cwd, &prefix).await?;
+            }
+            "mv" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let [src, dst] = &parts[..] else {
+                    return Err(anyhow!("Usage: mv <src> <dst> [--dry-run] [--yes]"));
+                };
+                let (src, dst) = (resolve_path(&cwd, src), resolve_path(&cwd, dst));
+                if dry_run {
+                    crate::util::plan_move(&db, &mut self.out, &src, &dst).await?;
+                    return Ok(Flow::Continue);
+                }
+                let prompt = format!("Move {} to {}?", display_path(&src), display_path(&dst));
+                if !self.approve(&cmd, &prompt, yes)? {
+                    return Ok(Flow::Continue);
+                }
+                crate::util::move_dir(&db, &src, &dst).await?;
+                self.children.lock().unwrap().clear();
+                // Follow the move if the session was inside the moved directory.
+                if cwd.starts_with(&src) {
+                    let mut moved = dst.clone();
+                    moved.extend_from_slice(&cwd[src.len()..]);
+                    self.set_cwd(moved);
+                }
+            }
+            "rename" => {
+                let yes = take_flag(&mut parts, &["--yes", "-y"]);
+                let dry_run = take_flag(&mut parts, &["--dry-run"]);
+                let [name] = &parts[..] else {
+                    return Err(anyhow!("Usage: rename <newname> [--dry-run] [--yes]"));
+                };
+                // One segment; quoting lets a name hold '/' ("a/b").
+                let name = match parse_path(name).as_slice() {
+                    [n] => n.clone(),
+                    _ => {
+                        return Err(anyhow!(
+                            "new name must be a single path segment (quote it to include '/')"
+                        ))
+                    }
+                };
+                let Some((_, parent)) = cwd.split_last() else {
+                    return Err(anyhow!("cannot rename the root directory"));
+                };
+                let mut dst = parent.to_vec();
+                dst.push(name);
+                if dst == cwd {
+                    return Err(anyhow!("{} already has that name", display_path(&cwd)));
+                }
+                if dry_run {
+                    crate::util::plan_move(&db, &mut self.out, &cwd, &dst).await?;
+                    return Ok(Flow::Continue);
+                }
+                let prompt = format!("Rename {} to {}?", display_path(&cwd), display_path(&dst));
+                if !self.approve(&cmd, &prompt, yes)? {
+                    return Ok(Flow::Continue);
+                }
+                crate::util::move_dir(&db, &cwd, &dst).await?;
+                self.children.lock().unwrap().clear();
+                self.set_cwd(dst);
+            }
+            "set" => {
+                let [key, value] = &parts[..] else {
+                    return Err(anyhow!("Usage: set <{}> <value>", SETTINGS.join("|")));
+                };
+                self.apply_setting(key, value)?;
+                // Scripts change settings for their own run only.
+                if self.interactive {
+                    let stored = match value.as_str() {
+                        "on" | "off" => crate::config::Value::Bool(value == "on"),
+                        v => v
+                            .parse()
+                            .map(crate::config::Value::Int)
+                            .unwrap_or_else(|_| crate::config::Value::Str(v.to_string())),
+                    };
+                    crate::config::store("settings", &config_key(key), &stored)?;
+                }
+            }
+            "show" => {
+                for key in SETTINGS {
+                    writeln!(
+                        self.out,
+                        "{:<12}{}",
+                        format!("{key}:").dimmed(),
+                        self.settings.get(key)
+                    )?;
+                }
+                if let Some(path) = crate::config::config_path() {
+                    writeln!(
+                        self.out,
+                        "{}",
+                        format!("saved in {}", path.display()).dimmed()
+                    )?;
+                }
+            }
+            "find" => {
+                let mut max_depth = None;
+                let mut args = vec![];
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    match tok.as_str() {
+                        "--max-depth" => max_depth = it.next().and_then(|n| n.parse().ok()),
+                        a => args.push(a),
+                    }
+                }
+                let Some(pattern) = args.first() else {
+                    return Err(anyhow!("Usage: find <glob> [path] [--max-depth n]"));
+                };
+                let pattern = glob::Pattern::new(pattern)?;
+                let path = args
+                    .get(1)
+                    .map(|p| resolve_path(&cwd, p))
+                    .unwrap_or(cwd.clone());
+                if crate::util::find_dirs(&db, &mut self.out, path, &pattern, max_depth).await? == 0
+                {
+                    writeln!(self.out, "(none)")?;
+                }
+            }
+            "grep" => {
+                let mut limit = Some(self.settings.limit);
+                let mut ignore_case = false;
+                let mut fmt = self.format_defaults();
+                let mut args = vec![];
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
+                    }
+                    match tok.as_str() {
+                        "--limit" | "-n" => {
+                            let n = it.next().and_then(|n| n.parse::<usize>().ok());
+                            limit = Some(n.ok_or_else(|| anyhow!("--limit requires a number"))?);
+                        }
+                        "--all" => limit = None,
+                        "--ignore-case" | "-i" => ignore_case = true,
+                        a => args.push(a),
+                    }
+                }
+                let Some(pattern) = args.first() else {
+                    return Err(anyhow!("Usage: grep <regex> [path] [--limit n|--all] [-i]"));
+                };
+                let re = regex::RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()?;
+                let path = args
+                    .get(1)
+                    .map(|p| resolve_path(&cwd, p))
+                    .unwrap_or(cwd.clone());
+                if crate::dump::grep_path(&db, &mut self.out, path, &re, limit, fmt).await? == 0 {
+                    writeln!(self.out, "(none)")?;
+                }
+            }
+            "groupby" => {
+                let mut limit = Some(crate::dump::GROUPBY_LIMIT);
+                let mut fmt = self.format_defaults();
+                let mut target = None;
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
+                    }
+                    match tok.as_str() {
+                        "--limit" | "-n" => {
+                            let n = it.next().and_then(|n| n.parse::<usize>().ok());
+                            limit = Some(n.ok_or_else(|| anyhow!("--limit requires a number"))?);
+                        }
+                        "--all" => limit = None,
+                        p if target.is_none() => target = Some(p),
+                        _ => return Err(anyhow!("Usage: groupby [path] [--limit n|--all]")),
+                    }
+                }
+                let path = target.map_or(cwd.clone(), |p| resolve_path(&cwd, p));
+                crate::dump::group_path(&db, &mut self.out, path, limit, fmt).await?;
+            }
+            "exists" => {
+                let [target] = &parts[..] else {
+                    return Err(anyhow!("Usage: exists <path>"));
+                };
+                let path = resolve_path(&cwd, target);
+                writeln!(self.out, "{}", crate::util::dir_exists(&db, &path).await?)?;
+            }
+            "find-prefix" => {
+                let [bytes] = &parts[..] else {
+                    return Err(anyhow!("Usage: find-prefix <bytes>"));
+                };
+                let key = crate::parse::parse_key_literal(bytes)?;
+                crate::util::find_prefix(&db, &mut self.out, &key).await?;
+            }
+            "get" | "cat" => {
+                let mut fmt = self.format_defaults();
+                let mut key = None;
+                let mut it = parts.iter();
+                while let Some(tok) = it.next() {
+                    if format_flag(tok, &mut it, &mut fmt)? {
+                        continue;
+                    }
+                    if key.is_none() {
+                        key = Some(tok);
+                    }
+                }
+                let Some(key) = key else {
+                    return Err(anyhow!("Usage: {cmd} <key>"));
+                };
+                let read = ReadOptions {
+                    snapshot: true,
+                    read_version,
+                };
+                let key = crate::parse::parse_key_literal(key)?;
+                crate::util::get_value(
+                    source(&self.txn, &db),
+                    &mut self.out,
+                    cwd.clone(),
+                    key,
+                    read,
+                    fmt,
+                )
+                .await?;
+            }
+            "watch" => {
+                let Some(key) = parts.first() else {
+                    return Err(anyhow!("Usage: watch <key>"));
+                };
+                let key = crate::parse::parse_key_literal(key)?;
+                crate::util::watch_key(&db, &mut self.out, cwd.clone(), key).await?;
+            }
+            "watch-dir" => {
+                let path = match parts.first() {
+                    Some(p) => resolve_path(&cwd, p),
+                    None => cwd.clone(),
+                };
+                crate::util::watch_dir(&db, &mut self.out, path).await?;
             }
             other => {
-                println!("Unknown command: {other}. Try 'help'.");
+                return Err(match crate::help::closest(other) {
+                    Some(name) => anyhow!("Unknown command: {other}. Did you mean '{name}'?"),
+                    None => anyhow!("Unknown command: {other}. Try 'help'."),
+                })
             }
         }
+        Ok(Flow::Continue)
     }
-    // Save history on exit
-    let _ = rl.save_history(&hist_path);
-    Ok(())
 }
 
-fn print_help() {
-    println!("Commands:");
-    println!("  help                 Show this help");
-    println!("  exit | quit          Exit the REPL");
-    println!("  pwd                  Print current directory path");
-    println!("  cd <path>            Change directory (use /, .., or relative)");
-    println!("  ls [path]            List subdirectories at path (default: current)");
-    println!("  scan [limit]         Print key=>value pairs in current dir (default 50)");
+// Applies a display flag shared by ls/scan, consuming its value from `rest`
+// if it takes one. Returns false if `tok` is not a display flag.
+fn format_flag<'a>(
+    tok: &str,
+    rest: &mut impl Iterator<Item = &'a String>,
+    fmt: &mut FormatOptions,
+) -> Result<bool> {
+    match tok {
+        "--pretty" => fmt.pretty = true,
+        "--hex" => fmt.hex_values = true,
+        "--hex-keys" => fmt.hex_keys = true,
+        "--int-hex" => fmt.int_hex = true,
+        "--b64" => fmt.b64 = true,
+        "--vs-hex" => fmt.vs_hex = true,
+        "--bare-floats" => fmt.bare_floats = true,
+        "--flat-colors" => fmt.flat_colors = true,
+        "--absolute-keys" => fmt.absolute_keys = true,
+        "--deep-decode" => fmt.deep_decode = true,
+        "--full" => fmt.width = None,
+        "--value-as" => {
+            fmt.value_as = Some(match rest.next().map(String::as_str) {
+                Some("int") => ValueAs::Int,
+                Some("uuid") => ValueAs::Uuid,
+                Some("utf8") => ValueAs::Utf8,
+                Some("hex") => ValueAs::Hex,
+                Some("little-endian-int") => ValueAs::LittleEndianInt,
+                _ => {
+                    return Err(anyhow!(
+                        "--value-as needs int, uuid, utf8, hex, or little-endian-int"
+                    ))
+                }
+            })
+        }
+        "--precision" => {
+            fmt.precision = Some(
+                rest.next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| anyhow!("--precision needs a number"))?,
+            )
+        }
+        "--max-bytes" | "--max-key-bytes" | "--max-value-bytes" => {
+            let n = rest
+                .next()
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| anyhow!("{tok} needs a number"))?;
+            if tok != "--max-value-bytes" {
+                fmt.max_key_bytes = n;
+            }
+            if tok != "--max-key-bytes" {
+                fmt.max_value_bytes = n;
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+// Removes every occurrence of a boolean flag from `parts`, reporting whether it was present.
+fn take_flag(parts: &mut Vec<String>, names: &[&str]) -> bool {
+    let before = parts.len();
+    parts.retain(|p| !names.contains(&p.as_str()));
+    parts.len() != before
+}
+
+// Removes `name <value>` from `parts`, returning the value.
+fn take_value(parts: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let Some(idx) = parts.iter().position(|p| p == name) else {
+        return Ok(None);
+    };
+    if idx + 1 == parts.len() {
+        return Err(anyhow!("{name} requires a value"));
+    }
+    let value = parts.remove(idx + 1);
+    parts.remove(idx);
+    Ok(Some(value))
+}
+
+fn resolve_path(cwd: &[String], target: &str) -> Vec<String> {
+    resolve_pattern(cwd, target).names()
+}
+
+// Names from cwd are quoted by display_path, so only the target's can be globs.
+fn resolve_pattern(cwd: &[String], target: &str) -> PathPattern {
+    if target.starts_with('/') {
+        parse_path_pattern(target)
+    } else {
+        // Relative paths go through parse_path whole, so their `..` can climb out of cwd.
+        parse_path_pattern(&format!("{}/{target}", display_path(cwd)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_setting_must_be_positive() {
+        let mut settings = Settings::new(false, false);
+        let err = settings.set("limit", "0").unwrap_err().to_string();
+        assert!(err.contains("at least 1"), "{err}");
+        assert!(settings.set("limit", "-3").is_err());
+        assert_eq!(settings.limit, 50);
+        settings.set("limit", "7").unwrap();
+        assert_eq!(settings.get("limit"), "7");
+    }
 }
@@ -0,0 +1,302 @@
+use crate::util::{describe_error, Client};
+use anyhow::{anyhow, Context, Result};
+use foundationdb::options::{StreamingMode, TransactionOption};
+use foundationdb::RangeOption;
+use futures_util::TryStreamExt;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+use std::io::Write;
+
+// Special key holding the machine-readable cluster status (what `fdbcli status json` prints).
+const STATUS_KEY: &[u8] = b"\xff\xff/status/json";
+
+// Special keyspace with one key per worker process registered with the
+// cluster controller, \xff\xff/worker_interfaces/<address>.
+const WORKERS_PREFIX: &[u8] = b"\xff\xff/worker_interfaces/";
+
+pub async fn read_status(db: &Client) -> Result<Value> {
+    let raw = crate::util::run(db, |trx, _| async move {
+        trx.set_option(TransactionOption::ReadSystemKeys)?;
+        Ok(trx.get(STATUS_KEY, false).await?.map(|v| v.to_vec()))
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?
+    .ok_or_else(|| anyhow!("cluster returned no status"))?;
+    serde_json::from_slice(&raw).context("cluster status is not valid JSON")
+}
+
+fn yes_no(v: Option<bool>) -> String {
+    match v {
+        Some(true) => "yes".green().to_string(),
+        Some(false) => "no".red().to_string(),
+        None => "unknown".dimmed().to_string(),
+    }
+}
+
+// Connection summary: which cluster file, which API, and whether the link is encrypted.
+pub async fn print_env<W: Write>(db: &Client, out: &mut W) -> Result<()> {
+    let status = read_status(db).await?;
+    let client = &status["client"];
+    let row = |out: &mut W, label: &str, value: String| -> Result<()> {
+        writeln!(out, "{:<16}{}", format!("{label}:").dimmed(), value)?;
+        Ok(())
+    };
+    row(
+        out,
+        "cluster file",
+        client["cluster_file"]["path"]
+            .as_str()
+            .unwrap_or("(unknown)")
+            .to_string(),
+    )?;
+    if client["cluster_file"]["up_to_date"].as_bool() == Some(false) {
+        row(out, "", "cluster file is out of date".yellow().to_string())?;
+    }
+    let api_version = db
+        .options()
+        .api_version
+        .map_or("unknown".to_string(), |v| v.to_string());
+    row(out, "api version", api_version)?;
+    row(
+        out,
+        "client library",
+        format!(
+            "supports API up to {}",
+            foundationdb::api::get_max_api_version()
+        ),
+    )?;
+    if let Some(proto) = status["cluster"]["protocol_version"].as_str() {
+        row(out, "protocol", proto.to_string())?;
+    }
+    // Coordinator addresses carry a `:tls` suffix when the cluster requires TLS.
+    let coordinators = client["coordinators"]["coordinators"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let tls = (!coordinators.is_empty()).then(|| {
+        coordinators
+            .iter()
+            .all(|c| c["address"].as_str().is_some_and(|a| a.ends_with(":tls")))
+    });
+    row(out, "tls", yes_no(tls))?;
+    let reachable = coordinators
+        .iter()
+        .filter(|c| c["reachable"].as_bool() == Some(true))
+        .count();
+    row(
+        out,
+        "coordinators",
+        format!("{reachable}/{} reachable", coordinators.len()),
+    )?;
+    row(
+        out,
+        "available",
+        yes_no(client["database_status"]["available"].as_bool()),
+    )?;
+    row(
+        out,
+        "healthy",
+        yes_no(client["database_status"]["healthy"].as_bool()),
+    )?;
+    Ok(())
+}
+
+pub fn format_size(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+// Selected fields of the status document, or the whole document with `raw`.
+pub async fn print_cluster_status<W: Write>(db: &Client, out: &mut W, raw: bool) -> Result<()> {
+    let status = read_status(db).await?;
+    if raw {
+        writeln!(out, "{}", serde_json::to_string_pretty(&status)?)?;
+        return Ok(());
+    }
+    let cluster = &status["cluster"];
+    let row = |out: &mut W, label: &str, value: String| -> Result<()> {
+        writeln!(out, "  {:<18}{}", format!("{label}:").dimmed(), value)?;
+        Ok(())
+    };
+    let num = |v: &Value| v.as_f64().unwrap_or(0.0);
+    let rate = |v: &Value| format!("{:.0}/s", num(&v["hz"]));
+
+    writeln!(out, "{}", "Health".bold())?;
+    row(
+        out,
+        "available",
+        yes_no(cluster["database_available"].as_bool()),
+    )?;
+    row(
+        out,
+        "healthy",
+        yes_no(status["client"]["database_status"]["healthy"].as_bool()),
+    )?;
+    let state = &cluster["data"]["state"];
+    if let Some(name) = state["name"].as_str() {
+        let desc = state["description"].as_str().unwrap_or_default();
+        row(out, "data state", format!("{name} {}", desc.dimmed()))?;
+    }
+    let config = &cluster["configuration"];
+    if let (Some(mode), Some(engine)) = (
+        config["redundancy_mode"].as_str(),
+        config["storage_engine"].as_str(),
+    ) {
+        row(out, "configuration", format!("{mode}, {engine}"))?;
+    }
+    for msg in cluster["messages"].as_array().into_iter().flatten() {
+        if let Some(desc) = msg["description"].as_str() {
+            row(out, "message", desc.yellow().to_string())?;
+        }
+    }
+
+    writeln!(out, "{}", "Processes".bold())?;
+    let processes = cluster["processes"].as_object();
+    row(
+        out,
+        "processes",
+        processes.map_or(0, |p| p.len()).to_string(),
+    )?;
+    row(
+        out,
+        "machines",
+        cluster["machines"]
+            .as_object()
+            .map_or(0, |m| m.len())
+            .to_string(),
+    )?;
+    let excluded = processes
+        .into_iter()
+        .flat_map(|p| p.values())
+        .filter(|p| p["excluded"].as_bool() == Some(true))
+        .count();
+    if excluded > 0 {
+        row(out, "excluded", excluded.to_string())?;
+    }
+
+    writeln!(out, "{}", "Data".bold())?;
+    let data = &cluster["data"];
+    row(
+        out,
+        "logical size",
+        format_size(num(&data["total_kv_size_bytes"])),
+    )?;
+    row(
+        out,
+        "disk used",
+        format_size(num(&data["total_disk_used_bytes"])),
+    )?;
+    row(
+        out,
+        "shards",
+        format!("{:.0}", num(&data["partitions_count"])),
+    )?;
+    let moving = &data["moving_data"];
+    row(
+        out,
+        "moving",
+        format!(
+            "{} in flight, {} queued",
+            format_size(num(&moving["in_flight_bytes"])),
+            format_size(num(&moving["in_queue_bytes"]))
+        ),
+    )?;
+
+    writeln!(out, "{}", "Workload".bold())?;
+    let ops = &cluster["workload"]["operations"];
+    let txns = &cluster["workload"]["transactions"];
+    row(out, "reads", rate(&ops["reads"]))?;
+    row(out, "writes", rate(&ops["writes"]))?;
+    row(
+        out,
+        "transactions",
+        format!(
+            "{} started, {} committed, {} conflicted",
+            rate(&txns["started"]),
+            rate(&txns["committed"]),
+            rate(&txns["conflicted"])
+        ),
+    )?;
+    Ok(())
+}
+
+// Addresses of the registered workers, in key order.
+async fn read_worker_addresses(db: &Client) -> Result<Vec<String>> {
+    crate::util::run(db, |trx, _| async move {
+        trx.set_option(TransactionOption::ReadSystemKeys)?;
+        let mut end = WORKERS_PREFIX.to_vec();
+        *end.last_mut().unwrap() += 1;
+        let mut opt: RangeOption = (WORKERS_PREFIX.to_vec(), end).into();
+        opt.mode = StreamingMode::WantAll;
+        let mut addresses = Vec::new();
+        let mut stream = trx.get_ranges_keyvalues(opt, false);
+        while let Some(item) = stream.try_next().await? {
+            let addr = &item.key()[WORKERS_PREFIX.len()..];
+            addresses.push(String::from_utf8_lossy(addr).into_owned());
+        }
+        Ok(addresses)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// One line per worker: address, process class, and the roles it holds. The
+// worker keys carry only addresses, so class and roles come from the status
+// document; when that can't be read the addresses are still listed.
+pub async fn print_workers<W: Write>(db: &Client, out: &mut W) -> Result<()> {
+    let addresses = read_worker_addresses(db).await?;
+    let status = read_status(db).await.ok();
+    let processes = status
+        .as_ref()
+        .and_then(|s| s["cluster"]["processes"].as_object());
+    writeln!(out, "-- {} workers --", addresses.len())?;
+    let width = addresses.iter().map(String::len).max().unwrap_or(0);
+    for addr in &addresses {
+        let process = processes.and_then(|p| {
+            p.values()
+                .find(|p| p["address"].as_str() == Some(addr.as_str()))
+        });
+        let Some(process) = process else {
+            writeln!(out, "  {}", addr.cyan())?;
+            continue;
+        };
+        let class = process["class_type"].as_str().unwrap_or("unset");
+        let mut roles: Vec<&str> = process["roles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r["role"].as_str())
+            .collect();
+        roles.dedup();
+        let roles = if roles.is_empty() {
+            "no roles".dimmed().to_string()
+        } else {
+            roles.join(",")
+        };
+        writeln!(
+            out,
+            "  {:<width$}  {:<12}{}",
+            addr.cyan(),
+            format!("[{class}]").dimmed(),
+            roles
+        )?;
+    }
+    if status.is_none() {
+        writeln!(
+            out,
+            "{}",
+            "-- cluster status unavailable; roles not shown --".dimmed()
+        )?;
+    }
+    Ok(())
+}
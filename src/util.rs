@@ -1,317 +1,2229 @@
+use crate::format::{
+    display_path, display_path_pattern, fit_width, format_bytes, format_element, format_hexdump,
+    format_key, format_raw, format_subspace_key, format_value, key_literal, paint_value,
+    project_key, try_utf8_or_bytes, FormatOptions, SchemaField, DEFAULT_MAX_BYTES,
+};
+use crate::format::{print_listings, ScanPrinter};
+use crate::parse::{PathPattern, SelectorSpec};
 use anyhow::{anyhow, Result};
 use foundationdb::directory::{Directory, DirectoryError, DirectoryLayer, DirectoryOutput};
-use foundationdb::tuple::{Element, TupleUnpack};
-use foundationdb::{RangeOption, Transaction};
-use futures_util::TryStreamExt;
+use foundationdb::options::{ConflictRangeType, StreamingMode, TransactionOption};
+use foundationdb::tuple::{Element, Subspace, TupleUnpack};
+use foundationdb::{
+    FdbBindingError, FdbError, KeySelector, MaybeCommitted, RangeOption, RetryableTransaction,
+    Transaction,
+};
+use futures_util::{StreamExt, TryStreamExt};
 use owo_colors::OwoColorize;
+use std::cell::{Cell, RefCell};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-pub fn parse_path(s: &str) -> Vec<String> {
-    let trimmed = s.trim();
-    if trimmed == "/" || trimmed.is_empty() {
-        return vec![];
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOptions {
+    pub snapshot: bool,
+    pub read_version: Option<i64>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            snapshot: true,
+            read_version: None,
+        }
+    }
+}
+
+impl ReadOptions {
+    // Sets up `trx` for a read: the client's tag and --tr-options, then the
+    // pinned version, if any.
+    pub fn apply(&self, client: &Client, trx: &Transaction) -> Result<(), FdbError> {
+        client.apply_tag(trx)?;
+        self.apply_untagged(client, trx)
+    }
+
+    // `apply` less the tag, for transactions `run` has already tagged.
+    fn apply_untagged(&self, client: &Client, trx: &Transaction) -> Result<(), FdbError> {
+        for option in &client.options.transaction_options {
+            trx.set_option(option.clone())?;
+        }
+        if client.options.low_read_priority {
+            // FDB_TR_OPTION_READ_PRIORITY_LOW; the 7.1 bindings have no name for it.
+            trx.set_raw_option(510, None)?;
+        }
+        if let Some(v) = self.read_version {
+            trx.set_read_version(v);
+        }
+        Ok(())
+    }
+}
+
+// Settings every transaction of a session shares, fixed when the client is
+// made and passed along with the database rather than held process-wide.
+#[derive(Clone, Debug, Default)]
+pub struct ClientOptions {
+    // Root the directory layer at custom node/content prefixes. A content
+    // prefix alone implies the conventional node prefix `content + \xFE`; a
+    // node prefix alone keeps content in the whole keyspace.
+    pub node_prefix: Option<Vec<u8>>,
+    pub content_prefix: Option<Vec<u8>>,
+    // --tag: marks every transaction the tool issues, so operators can find and
+    // throttle its traffic with tag-based throttling.
+    pub tag: Option<String>,
+    // --tr-option: set on every transaction the read commands run in.
+    pub transaction_options: Vec<TransactionOption>,
+    // --low-read-priority: storage servers serve these reads after normal ones
+    // (ReadPriorityLow, API 730 and newer; see `check`).
+    pub low_read_priority: bool,
+    // --verbose-retries: report each time a transaction is retried, and why, on stderr.
+    pub verbose_retries: bool,
+    // API version requested at boot (--api-version or a profile can lower it).
+    pub api_version: Option<i32>,
+}
+
+// The first API version with ReadPriorityLow.
+pub const READ_PRIORITY_API_VERSION: i32 = 730;
+
+impl ClientOptions {
+    // Refuses options the requested API version doesn't have, so they fail
+    // at startup instead of on the first read.
+    pub fn check(&self) -> Result<()> {
+        match self.api_version {
+            Some(v) if self.low_read_priority && v < READ_PRIORITY_API_VERSION => Err(anyhow!(
+                "--low-read-priority needs API version {READ_PRIORITY_API_VERSION} or newer \
+                 (FoundationDB 7.3 clients); this client uses {v}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// A database and the options every command run against it uses. Derefs to
+// the database.
+pub struct Client {
+    db: foundationdb::Database,
+    options: ClientOptions,
+    directory_layer: DirectoryLayer,
+    // Where the directory layer keeps its node metadata; the binding doesn't expose it.
+    node_prefix: Vec<u8>,
+}
+
+impl Client {
+    pub fn new(db: foundationdb::Database, options: ClientOptions) -> Self {
+        let (directory_layer, node_prefix) = match (&options.node_prefix, &options.content_prefix) {
+            (None, None) => (DirectoryLayer::default(), b"\xfe".to_vec()),
+            (node, content) => {
+                let content = content.clone().unwrap_or_default();
+                let node = node
+                    .clone()
+                    .unwrap_or_else(|| [content.as_slice(), b"\xfe"].concat());
+                let dl = DirectoryLayer::new(
+                    Subspace::from_bytes(node.as_slice()),
+                    Subspace::from_bytes(content),
+                    false,
+                );
+                (dl, node)
+            }
+        };
+        Self {
+            db,
+            options,
+            directory_layer,
+            node_prefix,
+        }
+    }
+
+    pub fn options(&self) -> &ClientOptions {
+        &self.options
+    }
+
+    // The directory layer all commands resolve paths through.
+    pub fn directory_layer(&self) -> &DirectoryLayer {
+        &self.directory_layer
+    }
+
+    pub fn apply_tag(&self, trx: &Transaction) -> Result<(), FdbError> {
+        match &self.options.tag {
+            Some(tag) => trx.set_option(TransactionOption::Tag(tag.clone())),
+            None => Ok(()),
+        }
+    }
+
+    // With `layer`, opening fails unless the directory was created with that
+    // layer (--layer), the way application bindings guard their directories.
+    pub async fn dir_for_path(
+        &self,
+        trx: &Transaction,
+        path: &[String],
+        layer: Option<&[u8]>,
+    ) -> Result<DirectoryOutput, DirectoryError> {
+        if path.is_empty() && layer.is_some_and(|l| !l.is_empty()) {
+            return Err(DirectoryError::Other(
+                "the root directory has no layer to check".to_string(),
+            ));
+        }
+        self.directory_layer
+            .open(trx, path, layer)
+            .await
+            .map_err(|e| match (e, layer) {
+                (DirectoryError::IncompatibleLayer, Some(layer)) => DirectoryError::Other(format!(
+                    "{} does not have layer {}",
+                    display_path(path),
+                    try_utf8_or_bytes(layer, &FormatOptions::default(), DEFAULT_MAX_BYTES)
+                )),
+                (e, _) => e,
+            })
+    }
+}
+
+impl std::ops::Deref for Client {
+    type Target = foundationdb::Database;
+
+    fn deref(&self) -> &foundationdb::Database {
+        &self.db
+    }
+}
+
+// Where `ls`/`scan`/`get` read: a fresh (retried) transaction per command, or
+// one the REPL holds open between `begin` and `end`.
+#[derive(Clone, Copy)]
+pub enum Source<'a> {
+    Db(&'a Client),
+    Trx(&'a Client, &'a Transaction),
+}
+
+// `cd --layer`: fails unless the directory at `path` has `layer`.
+pub async fn check_layer(db: &Client, path: &[String], layer: &[u8]) -> Result<()> {
+    run(db, |trx, _| async move {
+        db.dir_for_path(&trx, path, Some(layer)).await?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Columns of the terminal on stdout; None when stdout is piped or redirected.
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        // SAFETY: TIOCGWINSZ only fills in the winsize struct passed to it.
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+            && size.ws_col > 0
+        {
+            return Some(size.ws_col.into());
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|&c| c > 0)
+}
+
+// Columns of the bar itself; the percentage and sizes follow it.
+const PROGRESS_WIDTH: usize = 30;
+
+// How often the bar is redrawn; also how long a read runs before it shows.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Estimated progress of a long read, redrawn in place on stderr. The total is
+// the cluster's sampled size estimate, so the bar holds at 99% if the read
+// outruns it. The line is cleared when the bar is dropped.
+pub struct ProgressBar {
+    total: u64,
+    done: u64,
+    drawn: Instant,
+    visible: bool,
+}
+
+impl ProgressBar {
+    pub fn start(estimate: i64) -> Self {
+        Self {
+            total: estimate.max(0) as u64,
+            done: 0,
+            drawn: Instant::now(),
+            visible: false,
+        }
+    }
+
+    pub fn add(&mut self, bytes: usize) {
+        self.done += bytes as u64;
+        if self.total == 0 || self.drawn.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.drawn = Instant::now();
+        self.visible = true;
+        let frac = (self.done as f64 / self.total as f64).min(0.99);
+        let filled = (frac * PROGRESS_WIDTH as f64) as usize;
+        let size = crate::status::format_size;
+        eprint!(
+            "\r{}{} {:>3}% {} of ~{}\x1b[K",
+            "#".repeat(filled),
+            "-".repeat(PROGRESS_WIDTH - filled).dimmed(),
+            (frac * 100.0) as u32,
+            size(self.done as f64),
+            size(self.total as f64)
+        );
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        if self.visible {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+// `db.run` with the client's tag and --verbose-retries logging. Errors from the closure are logged
+// with their code on their way to the retry loop; a failed commit happens
+// inside `run`, so it only shows up as the next attempt starting.
+pub async fn run<F, Fut, T>(db: &Client, closure: F) -> Result<T, FdbBindingError>
+where
+    F: Fn(RetryableTransaction, MaybeCommitted) -> Fut,
+    Fut: std::future::Future<Output = Result<T, FdbBindingError>>,
+{
+    // Every transaction the tool runs carries the --tag, set here once so
+    // no closure has to remember it.
+    let closure = |trx: RetryableTransaction, maybe_committed| {
+        let tagged = db.apply_tag(&trx);
+        let fut = closure(trx, maybe_committed);
+        async move {
+            tagged?;
+            fut.await
+        }
+    };
+    if !db.options.verbose_retries {
+        return db.run(closure).await;
+    }
+    let attempt = AtomicUsize::new(0);
+    // Whether the previous attempt's closure failed (and was already logged).
+    let failed = AtomicBool::new(false);
+    let log = |msg: String| eprintln!("{}", format!("retry: {msg}").yellow());
+    db.run(|trx, maybe_committed| {
+        let n = attempt.fetch_add(1, Ordering::Relaxed) + 1;
+        if n > 1 && !failed.load(Ordering::Relaxed) {
+            log(format!("attempt {} failed to commit; retrying", n - 1));
+        }
+        failed.store(false, Ordering::Relaxed);
+        let fut = closure(trx, maybe_committed);
+        let (failed, log) = (&failed, &log);
+        async move {
+            let result = fut.await;
+            if let Err(e) = &result {
+                failed.store(true, Ordering::Relaxed);
+                if let Some(e) = e.get_fdb_error() {
+                    let next = if e.is_retryable() {
+                        "retrying"
+                    } else {
+                        "giving up"
+                    };
+                    log(format!("attempt {n}: {}; {next}", describe_fdb_error(e)));
+                }
+            }
+            result
+        }
+    })
+    .await
+}
+
+// `run` for a read at `read`, which is applied to every attempt. A pinned
+// version never moves, so once it is too old (1007) or ahead of the cluster
+// (1009) every retry would fail the same way; those errors end the run.
+pub async fn run_read<F, Fut, T>(
+    db: &Client,
+    read: ReadOptions,
+    closure: F,
+) -> Result<T, FdbBindingError>
+where
+    F: Fn(RetryableTransaction, MaybeCommitted) -> Fut,
+    Fut: std::future::Future<Output = Result<T, FdbBindingError>>,
+{
+    run(db, |trx, maybe_committed| {
+        let applied = read.apply_untagged(db, &trx);
+        let fut = closure(trx, maybe_committed);
+        async move {
+            applied?;
+            fut.await.map_err(|e| pinned_version_error(e, read))
+        }
+    })
+    .await
+}
+
+// 1007/1009 on a pinned read version. As a CustomError it carries no
+// FdbError, so `run` doesn't retry it.
+#[derive(Debug)]
+struct PinnedVersionError {
+    error: FdbError,
+    version: i64,
+}
+
+impl std::fmt::Display for PinnedVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let why = if self.error.code() == 1007 {
+            "has expired"
+        } else {
+            "is ahead of the cluster"
+        };
+        write!(
+            f,
+            "{}; pinned read version {} {why} and retrying won't change it \
+             (setversion another, or clearversion to read at the latest)",
+            describe_fdb_error(self.error),
+            self.version
+        )
+    }
+}
+
+impl std::error::Error for PinnedVersionError {}
+
+fn pinned_version_error(e: FdbBindingError, read: ReadOptions) -> FdbBindingError {
+    match (read.read_version, e.get_fdb_error()) {
+        (Some(version), Some(error)) if matches!(error.code(), 1007 | 1009) => {
+            FdbBindingError::CustomError(Box::new(PinnedVersionError { error, version }))
+        }
+        _ => e,
+    }
+}
+
+// Byte prefix of a directory's contents. Partitions have none (their keys live
+// in their subdirectories), so name the directory instead of the binding's error.
+pub fn content_prefix(dir: &DirectoryOutput) -> Result<&[u8], FdbBindingError> {
+    dir.bytes().map_err(|_| {
+        FdbBindingError::CustomError(
+            format!(
+                "{} is a directory partition; its keys live in its subdirectories",
+                display_path(dir.get_path())
+            )
+            .into(),
+        )
+    })
+}
+
+// Surfaces a write failure (e.g. a full disk under --output) from inside a transaction closure.
+fn io_err(e: std::io::Error) -> FdbBindingError {
+    FdbBindingError::CustomError(Box::new(e))
+}
+
+// One-line message for a binding, directory, or FDB error, in place of the
+// nested Debug output (`DirectoryError(FdbError(...))`).
+pub fn describe_error(e: impl Into<FdbBindingError>) -> String {
+    match e.into() {
+        FdbBindingError::NonRetryableFdbError(e) => describe_fdb_error(e),
+        FdbBindingError::DirectoryError(e) => describe_directory_error(e),
+        FdbBindingError::HcaError(e) => format!("prefix allocation failed: {e:?}"),
+        FdbBindingError::PackError(e) => format!("cannot decode tuple: {e:?}"),
+        FdbBindingError::CustomError(e) => match e.downcast::<FdbError>() {
+            Ok(e) => describe_fdb_error(*e),
+            Err(e) => e.to_string(),
+        },
+        e => format!("{e:?}"),
+    }
+}
+
+fn describe_directory_error(e: DirectoryError) -> String {
+    match e {
+        DirectoryError::DirectoryDoesNotExists | DirectoryError::PathDoesNotExists => {
+            "no such directory".to_string()
+        }
+        DirectoryError::ParentDirDoesNotExists => "parent directory does not exist".to_string(),
+        DirectoryError::DirAlreadyExists => "directory already exists".to_string(),
+        DirectoryError::NoPathProvided => "no path given".to_string(),
+        DirectoryError::CannotModifyRootDirectory => "cannot modify the root directory".to_string(),
+        DirectoryError::CannotMoveRootDirectory => "cannot move the root directory".to_string(),
+        DirectoryError::IncompatibleLayer => "directory exists with a different layer".to_string(),
+        DirectoryError::BadDestinationDirectory | DirectoryError::CannotMoveBetweenSubdirectory => {
+            "destination cannot be inside the source directory".to_string()
+        }
+        DirectoryError::CannotMoveBetweenPartition => {
+            "cannot move a directory between partitions".to_string()
+        }
+        DirectoryError::DirectoryPrefixInUse => "prefix is already in use".to_string(),
+        DirectoryError::PrefixNotEmpty => "prefix is not empty".to_string(),
+        DirectoryError::PrefixNotAllowed | DirectoryError::CannotPrefixInPartition => {
+            "manual prefixes are not allowed here".to_string()
+        }
+        DirectoryError::CannotOpenDirectoryPartition
+        | DirectoryError::CannotGetKeyDirectoryPartition
+        | DirectoryError::CannotPackDirectoryPartition
+        | DirectoryError::CannotUnpackDirectoryPartition
+        | DirectoryError::CannotRangeDirectoryPartition => {
+            "the root of a partition has no keys of its own".to_string()
+        }
+        DirectoryError::Version(v) => format!("unsupported directory layer version: {v}"),
+        DirectoryError::FdbError(e) => describe_fdb_error(e),
+        DirectoryError::IoError(e) => e.to_string(),
+        DirectoryError::Other(msg) => msg,
+        e => format!("{e:?}"),
+    }
+}
+
+fn describe_fdb_error(e: FdbError) -> String {
+    let hint = match e.code() {
+        1007 => "the read version is older than the ~5 seconds of history FoundationDB keeps",
+        1009 => "the read version is newer than the cluster's latest version",
+        1040 => "the cluster runs a FoundationDB version this client library cannot talk to; install a matching libfdb_c",
+        1020 => "conflicted with a concurrent write; retry",
+        1021 => "the commit may or may not have been applied",
+        1031 | 1004 => "timed out waiting for the cluster",
+        2101 => "transaction exceeds the 10MB size limit",
+        2102 => "key exceeds the 10KB size limit",
+        2103 => "value exceeds the 100KB size limit",
+        _ => return format!("{} (code {})", e.message(), e.code()),
+    };
+    format!("{}: {} (code {})", e.message(), hint, e.code())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LsSort {
+    Name,
+    NameDesc,
+    // Largest key count first; needs --counts.
+    Size,
+    // Allocated prefix, which roughly follows creation order; opens every child.
+    Prefix,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    // Colorless, one JSON document per command.
+    Json,
+}
+
+// How `scan` lays out its rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScanFormat {
+    // Numbered rows, printed as they are read.
+    #[default]
+    List,
+    // A bordered key/value table, drawn once every row is read.
+    Table,
+}
+
+#[derive(Clone, Debug)]
+pub struct LsOptions {
+    // Show a (capped) key count next to each subdirectory.
+    pub counts: bool,
+    // None keeps the directory layer's (lexicographic) order.
+    pub sort: Option<LsSort>,
+    // Show each subdirectory's allocated prefix and layer.
+    pub long: bool,
+    pub format: OutputFormat,
+    // How many keys to preview; 0 lists directories only.
+    pub keys: usize,
+    // Fail unless the listed directory has this layer.
+    pub layer: Option<Vec<u8>>,
+    // Children opened at once for --counts, --long, and --sort prefix.
+    pub parallel: usize,
+}
+
+// Default for `ls --parallel`.
+pub const LS_PARALLEL: usize = 16;
+
+impl Default for LsOptions {
+    fn default() -> Self {
+        Self {
+            counts: false,
+            sort: None,
+            long: false,
+            format: OutputFormat::Text,
+            keys: 50,
+            layer: None,
+            parallel: LS_PARALLEL,
+        }
+    }
+}
+
+// Per-directory key counts stop here so one huge child doesn't stall `ls --counts`.
+pub const COUNT_CAP: usize = 10_000;
+
+// One subdirectory in an `ls` listing; counts and prefix/layer are only
+// filled in for --counts / --long, which open every child.
+pub struct DirEntry {
+    pub name: String,
+    pub partition: bool,
+    // Exact up to COUNT_CAP; COUNT_CAP + 1 means "more than COUNT_CAP".
+    pub count: Option<usize>,
+    pub prefix: Option<Vec<u8>>,
+    pub layer: Vec<u8>,
+}
+
+// A sampled key, rendered inside the transaction since decoding needs the directory.
+pub struct KeyEntry {
+    pub key: String,
+    pub value: String,
+    // Relative to the directory.
+    pub raw_key: Vec<u8>,
+    pub raw_value: Vec<u8>,
+}
+
+// What `ls` read for one directory; `format::print_listings` prints it.
+pub struct Listing {
+    pub path: Vec<String>,
+    pub dirs: Vec<DirEntry>,
+    // None at the root, for partitions, and with --keys 0.
+    pub keys: Option<Vec<KeyEntry>>,
+    // More keys than `LsOptions::keys` follow the ones in `keys`.
+    pub more: bool,
+    // The listed directory is a partition: it has subdirectories but no keys of its own.
+    pub partition: bool,
+    pub timing: ReadTiming,
+}
+
+impl Listing {
+    // Directories and keys listed.
+    pub fn count(&self) -> usize {
+        self.dirs.len() + self.keys.as_ref().map_or(0, Vec::len)
+    }
+}
+
+// Where the time of a read went, for --timing: "transaction" runs until the
+// directory is open (or, for ls, listed), "iterate" is the rest.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReadTiming {
+    pub transaction: Duration,
+    pub iterate: Duration,
+}
+
+impl ReadTiming {
+    fn since(started: Instant, opened: Instant) -> Self {
+        let total = started.elapsed();
+        let transaction = opened.saturating_duration_since(started);
+        Self {
+            transaction,
+            iterate: total.saturating_sub(transaction),
+        }
     }
-    trimmed
-        .trim_start_matches('/')
-        .split('/')
-        .filter(|p| !p.is_empty())
-        .map(|s| s.to_string())
-        .collect()
 }
 
-pub async fn dir_for_path(
+// Prints the listings of `path` and returns them.
+pub async fn ls_path<W: Write>(
+    src: Source<'_>,
+    out: &mut W,
+    path: PathPattern,
+    ls: LsOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Vec<Listing>> {
+    let globbed = path.has_glob();
+    let listings = list_path(src, path, &ls, read, fmt).await?;
+    print_listings(out, &listings, globbed, &ls, fmt)?;
+    Ok(listings)
+}
+
+// The listing of a directory, or with glob segments (`ls /app/*/config`), of
+// every matching directory in list order. A plain `Vec<String>` path converts
+// with every name literal; parse::parse_path_pattern finds the globs in one
+// typed by a user.
+pub async fn list_path(
+    src: Source<'_>,
+    path: impl Into<PathPattern>,
+    ls: &LsOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Vec<Listing>> {
+    let path = path.into();
+    if ls.sort == Some(LsSort::Size) && !ls.counts {
+        return Err(anyhow!("--sort size needs --counts"));
+    }
+    if !path.has_glob() {
+        return Ok(vec![ls_listing(src, &path.names(), ls, read, fmt).await?]);
+    }
+    let paths = expand_path_glob(src, &path).await?;
+    if paths.is_empty() {
+        return Err(anyhow!(
+            "no directories match {}",
+            display_path_pattern(&path)
+        ));
+    }
+    let mut listings = Vec::new();
+    for path in paths {
+        listings.push(ls_listing(src, &path, ls, read, fmt).await?);
+    }
+    Ok(listings)
+}
+
+// Every existing directory a path with glob segments names, in list order.
+// Each segment from the first glob on costs one listing per directory
+// matched so far; plain segments after it keep only directories that have
+// that child.
+pub async fn expand_path_glob(src: Source<'_>, path: &PathPattern) -> Result<Vec<Vec<String>>> {
+    let mut matched = vec![Vec::new()];
+    let mut globbed = false;
+    for (name, glob) in &path.segments {
+        globbed |= glob;
+        if !globbed {
+            matched[0].push(name.clone());
+            continue;
+        }
+        // A literal name after a glob matches only itself.
+        let pattern = if *glob {
+            glob::Pattern::new(name)
+        } else {
+            glob::Pattern::new(&glob::Pattern::escape(name))
+        }
+        .map_err(|e| anyhow!("invalid pattern '{name}': {e}"))?;
+        let mut next = Vec::new();
+        for parent in matched {
+            for child in list_dirs_in(src, &parent).await? {
+                if pattern.matches(&child) {
+                    let mut path = parent.clone();
+                    path.push(child);
+                    next.push(path);
+                }
+            }
+        }
+        matched = next;
+    }
+    Ok(matched)
+}
+
+async fn list_dirs_in(src: Source<'_>, path: &[String]) -> Result<Vec<String>> {
+    match src {
+        Source::Db(db) => list_dirs(db, path).await,
+        Source::Trx(db, trx) => db
+            .directory_layer()
+            .list(trx, path)
+            .await
+            .map_err(|e| anyhow!(describe_error(e))),
+    }
+}
+
+// Reads and sorts one directory's listing.
+async fn ls_listing(
+    src: Source<'_>,
+    path: &[String],
+    ls: &LsOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Listing> {
+    let started = Instant::now();
+    let listed = Cell::new(started);
+    let mut listing = match src {
+        Source::Db(db) => {
+            run_read(db, read, |trx, _| {
+                let listed = &listed;
+                async move { read_listing(db, &trx, path, ls, read, fmt, listed).await }
+            })
+            .await
+        }
+        Source::Trx(db, trx) => read_listing(db, trx, path, ls, read, fmt, &listed).await,
+    }
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    listing.timing = ReadTiming::since(started, listed.get());
+
+    match ls.sort {
+        None | Some(LsSort::Name) => {}
+        Some(LsSort::NameDesc) => listing.dirs.reverse(),
+        // Stable, so equal counts stay in name order; partitions (no count) go last.
+        Some(LsSort::Size) => listing.dirs.sort_by_key(|d| std::cmp::Reverse(d.count)),
+        // Partitions (no prefix of their own) go last here too.
+        Some(LsSort::Prefix) => listing
+            .dirs
+            .sort_by(|a, b| (a.partition, &a.prefix).cmp(&(b.partition, &b.prefix))),
+    }
+    Ok(listing)
+}
+
+async fn read_listing(
+    db: &Client,
     trx: &Transaction,
     path: &[String],
-) -> Result<DirectoryOutput, DirectoryError> {
-    let dl = DirectoryLayer::default();
+    ls: &LsOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+    listed: &Cell<Instant>,
+) -> Result<Listing, FdbBindingError> {
+    if ls.layer.is_some() {
+        db.dir_for_path(trx, path, ls.layer.as_deref()).await?;
+    }
+    let dl = db.directory_layer();
+    let names = dl.list(trx, path).await?;
+    listed.set(Instant::now());
+    let open_children = ls.counts || ls.long || ls.sort == Some(LsSort::Prefix);
+    // Children are opened (and counted) up to ls.parallel at a time within
+    // the one transaction; `buffered` keeps them in listing order.
+    let dirs: Vec<DirEntry> = futures_util::stream::iter(names)
+        .map(|name| async move {
+            let mut entry = DirEntry {
+                name,
+                partition: false,
+                count: None,
+                prefix: None,
+                layer: Vec::new(),
+            };
+            if open_children {
+                let mut child = path.to_vec();
+                child.push(entry.name.clone());
+                let dir = db.dir_for_path(trx, &child, None).await?;
+                entry.prefix = dir.bytes().ok().map(|b| b.to_vec());
+                entry.partition = entry.prefix.is_none();
+                entry.layer = dir.get_layer().to_vec();
+                if ls.counts {
+                    entry.count = count_keys(trx, &dir, read.snapshot).await?;
+                }
+            }
+            Ok::<_, FdbBindingError>(entry)
+        })
+        .buffered(ls.parallel.max(1))
+        .try_collect()
+        .await?;
+
+    let mut listing = Listing {
+        path: path.to_vec(),
+        dirs,
+        keys: None,
+        more: false,
+        partition: false,
+        timing: ReadTiming::default(),
+    };
+    // Keys (first N). Skip at root (no content keys at the directory layer root).
     if path.is_empty() {
-        dl.open(trx, &[], None).await
+        return Ok(listing);
+    }
+    // A partition lists its own children (list() descends into it),
+    // but its contents only live in those subdirectories.
+    let dir = db.dir_for_path(trx, path, None).await?;
+    let Ok(prefix) = dir.bytes() else {
+        listing.partition = true;
+        return Ok(listing);
+    };
+    if ls.keys == 0 {
+        return Ok(listing);
+    }
+    let prefix_len = prefix.len();
+    let (begin, end) = dir.range()?;
+    let mut opt: RangeOption = (begin, end).into();
+    opt.limit = Some(ls.keys + 1);
+    let mut keys = Vec::new();
+    let mut stream = trx.get_ranges_keyvalues(opt, read.snapshot);
+    while let Some(item) = stream.try_next().await? {
+        if keys.len() == ls.keys {
+            listing.more = true;
+            break;
+        }
+        keys.push(KeyEntry {
+            key: format_key(&dir, item.key(), &fmt),
+            value: format_value(item.value(), &fmt),
+            raw_key: item.key()[prefix_len..].to_vec(),
+            raw_value: item.value().to_vec(),
+        });
+    }
+    listing.keys = Some(keys);
+    Ok(listing)
+}
+
+// Exact key count of a directory up to COUNT_CAP (COUNT_CAP + 1 beyond that);
+// None for partitions, which have no content range of their own.
+async fn count_keys(
+    trx: &Transaction,
+    dir: &DirectoryOutput,
+    snapshot: bool,
+) -> Result<Option<usize>, FdbBindingError> {
+    let Ok((begin, end)) = dir.range() else {
+        return Ok(None);
+    };
+    let mut opt: RangeOption = (begin, end).into();
+    opt.limit = Some(COUNT_CAP + 1);
+    opt.mode = StreamingMode::WantAll;
+    let mut n = 0usize;
+    let mut stream = trx.get_ranges_keyvalues(opt, snapshot);
+    while stream.try_next().await?.is_some() {
+        n += 1;
+    }
+    Ok(Some(n))
+}
+
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    // None streams the whole range.
+    pub limit: Option<usize>,
+    pub prefix: Option<Vec<u8>>,
+    // Resume strictly after this key (relative to the directory).
+    pub after: Option<Vec<u8>>,
+    // Treat `prefix` as a tuple subspace: show keys with it stripped, decoding
+    // the remaining elements.
+    pub subspace: bool,
+    // Print keys only; values are still transferred but not rendered.
+    pub keys_only: bool,
+    // None lets the scan pick (WantAll when unlimited).
+    pub streaming: Option<StreamingMode>,
+    // Show only these elements (by index) of tuple keys, like `cut`.
+    pub fields: Option<Vec<usize>>,
+    // Label the elements of tuple keys with these names.
+    pub schema: Option<Vec<SchemaField>>,
+    // Flag keys whose decoded tuple does not pack back to the same bytes.
+    pub verify_roundtrip: bool,
+    // Refuse an unlimited scan whose estimated size is over this many bytes.
+    pub max_scan_bytes: Option<u64>,
+    // Key selectors for the ends of the range, in place of the directory's bounds.
+    pub begin_sel: Option<SelectorSpec>,
+    pub end_sel: Option<SelectorSpec>,
+    // Draw a progress bar on stderr during unlimited scans.
+    pub progress_bar: bool,
+    // Show the last n keys instead of the first (read backwards, printed in key order).
+    pub tail: Option<usize>,
+    pub format: ScanFormat,
+    // Fail unless the scanned directory has this layer.
+    pub layer: Option<Vec<u8>>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            limit: Some(50),
+            prefix: None,
+            after: None,
+            subspace: false,
+            keys_only: false,
+            streaming: None,
+            fields: None,
+            schema: None,
+            verify_roundtrip: false,
+            max_scan_bytes: None,
+            begin_sel: None,
+            end_sel: None,
+            progress_bar: false,
+            tail: None,
+            format: ScanFormat::List,
+            layer: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Scanned {
+    // Rows read.
+    pub rows: usize,
+    // The last key read (relative to the directory) when the limit cut the
+    // scan short, so callers can resume with `after`.
+    pub cursor: Option<Vec<u8>>,
+    // Keys --verify-roundtrip flagged.
+    pub mismatches: usize,
+    // Bytes of the rows read.
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+    pub timing: ReadTiming,
+}
+
+// One row of a scan.
+pub struct ScanRow {
+    // Position in the scan, from 1.
+    pub index: usize,
+    // Relative to the directory.
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    // The key as `scan` shows it: decoded, and cut or labeled by --fields and --schema.
+    pub key_text: String,
+    // Why the key fails --verify-roundtrip, when it does.
+    pub problem: Option<String>,
+}
+
+// Where a scan's rows go as they are read. `format::ScanPrinter` prints them;
+// a Vec<ScanRow> collects them. A scan longer than one transaction continues
+// in the next, and `resumed` marks each continuation.
+pub trait ScanSink {
+    // The directory is open; `prefix` is its content prefix.
+    fn start(&mut self, _path: &[String], _prefix: &[u8]) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn resumed(&mut self, _after: &[u8], _version: i64) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn row(&mut self, row: ScanRow) -> std::io::Result<()>;
+
+    // The scan completed; not called when it fails.
+    fn finish(&mut self, _scanned: &Scanned) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ScanSink for Vec<ScanRow> {
+    fn row(&mut self, row: ScanRow) -> std::io::Result<()> {
+        self.push(row);
+        Ok(())
+    }
+}
+
+pub async fn scan_path<W: Write>(
+    src: Source<'_>,
+    out: &mut W,
+    path: Vec<String>,
+    scan: ScanOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Scanned> {
+    if scan.format == ScanFormat::Table {
+        if scan.limit.is_none() && scan.tail.is_none() {
+            return Err(anyhow!(
+                "--format table holds every row to size its columns; give it a limit"
+            ));
+        }
+        if scan.verify_roundtrip {
+            return Err(anyhow!(
+                "--verify-roundtrip prints its warnings between rows; use --format list"
+            ));
+        }
+    }
+    let mut printer = ScanPrinter::new(out, &scan, fmt);
+    let scanned = scan_rows(src, &mut printer, path, scan.clone(), read, fmt).await?;
+    Ok(scanned)
+}
+
+// FDB treats a zero limit as "no limit"; unbounded scans must be asked for explicitly.
+fn check_limit(limit: Option<usize>) -> Result<()> {
+    if limit == Some(0) {
+        return Err(anyhow!(
+            "limit must be at least 1 (use --all to scan everything)"
+        ));
+    }
+    Ok(())
+}
+
+// Reads the rows `scan` selects under `path` into `sink`.
+pub async fn scan_rows<S: ScanSink>(
+    src: Source<'_>,
+    sink: &mut S,
+    path: Vec<String>,
+    mut scan: ScanOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Scanned> {
+    // A tail reads exactly its n rows, so the limit never cuts it short.
+    if let Some(n) = scan.tail {
+        scan.limit = Some(n);
+    }
+    check_limit(scan.limit)?;
+    let state = ScanState {
+        sink: RefCell::new(sink),
+        progress: RefCell::new(ScanProgress::default()),
+    };
+    let started = Instant::now();
+    let res = match src {
+        // Each retry db.run makes (after backing off) picks up after the last
+        // key read, so scans longer than one transaction still complete.
+        Source::Db(db) => {
+            run_read(db, read, |trx, _| {
+                let path = path.clone();
+                let scan = scan.clone();
+                let state = &state;
+                async move { scan_in(db, &trx, state, &path, &scan, read, fmt).await }
+            })
+            .await
+        }
+        Source::Trx(db, trx) => scan_in(db, trx, &state, &path, &scan, read, fmt).await,
+    }
+    .map_err(|e| anyhow!(describe_scan_error(e)));
+    let ScanState { sink, progress } = state;
+    let mut p = progress.into_inner();
+    p.bar = None;
+    let scanned = Scanned {
+        rows: p.printed,
+        cursor: res?,
+        mismatches: p.mismatches,
+        key_bytes: p.key_bytes,
+        value_bytes: p.value_bytes,
+        timing: ReadTiming::since(started, p.opened.unwrap_or(started)),
+    };
+    sink.into_inner().finish(&scanned)?;
+    Ok(scanned)
+}
+
+// Why a key (relative to its directory) would mislead as a decoded tuple:
+// it isn't one, or the tuple it decodes to packs to different bytes.
+fn roundtrip_problem(key: &[u8], max: usize) -> Option<String> {
+    match Element::unpack_root(key) {
+        Err(_) => Some("not a tuple; shown as bytes".to_string()),
+        Ok(el) => {
+            let packed = foundationdb::tuple::pack(&el);
+            (packed != key).then(|| {
+                format!(
+                    "decoded tuple packs to {}, not the stored bytes {}",
+                    format_bytes(&packed, max),
+                    format_bytes(key, max)
+                )
+            })
+        }
+    }
+}
+
+// Where a scan's rows go and how far it got, shared by the transactions it
+// is retried in.
+struct ScanState<'s, S> {
+    sink: RefCell<&'s mut S>,
+    progress: RefCell<ScanProgress>,
+}
+
+// How far a scan got, carried across the transactions it is retried in.
+#[derive(Default)]
+struct ScanProgress {
+    // Transactions started so far.
+    attempts: usize,
+    // Rows read so far.
+    printed: usize,
+    // Last key read (relative to the directory).
+    last: Option<Vec<u8>>,
+    // When the directory was first opened, for --timing.
+    opened: Option<Instant>,
+    // Bytes of the rows printed so far, for the summary line.
+    key_bytes: usize,
+    value_bytes: usize,
+    // Keys --verify-roundtrip flagged.
+    mismatches: usize,
+    // Shown by unlimited scans with `progress_bar`.
+    bar: Option<ProgressBar>,
+}
+
+// transaction_too_old means the read outlived the 5-second window it could
+// not continue past (a pinned version or a `begin` transaction); say how to
+// get under it instead of leaving just the error. A pinned version's error
+// comes from run_read as a PinnedVersionError.
+fn too_old_advice(msg: String) -> String {
+    format!(
+        "{msg}; read less per transaction with --limit (continuing with --after or 'more'), \
+         or use --all without a pinned version or 'begin' so it continues in new transactions"
+    )
+}
+
+fn describe_scan_error(e: FdbBindingError) -> String {
+    let code = match &e {
+        FdbBindingError::CustomError(c) => c
+            .downcast_ref::<PinnedVersionError>()
+            .map(|p| p.error.code()),
+        e => e.get_fdb_error().map(FdbError::code),
+    };
+    let too_old = code == Some(1007);
+    let msg = describe_error(e);
+    if too_old {
+        too_old_advice(msg)
     } else {
-        dl.open(trx, path, None).await
+        msg
     }
 }
 
-pub async fn ls_path(db: &foundationdb::Database, path: Vec<String>) -> Result<()> {
-    const SAMPLE: usize = 50;
-    db.run(|trx, _| {
-        let path = path.clone();
-        async move {
-            let dl = DirectoryLayer::default();
-            if path.is_empty() {
-                println!("/:");
-            } else {
-                println!("/{}:", path.join("/"));
-            }
+async fn scan_in<S: ScanSink>(
+    db: &Client,
+    trx: &Transaction,
+    state: &ScanState<'_, S>,
+    path: &[String],
+    scan: &ScanOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<Option<Vec<u8>>, FdbBindingError> {
+    let (attempt, printed, resume) = {
+        let mut p = state.progress.borrow_mut();
+        p.attempts += 1;
+        (p.attempts, p.printed, p.last.clone())
+    };
+    let dir = db.dir_for_path(trx, path, scan.layer.as_deref()).await?;
+    state
+        .progress
+        .borrow_mut()
+        .opened
+        .get_or_insert_with(Instant::now);
+    let dir_prefix = content_prefix(&dir)?.to_vec();
+    let (mut begin, end) = if let Some(pfx) = scan.prefix.as_ref() {
+        let mut start = dir_prefix.clone();
+        start.extend_from_slice(pfx);
+        let end = strinc(start.clone())?;
+        (start, end)
+    } else {
+        dir.range()?
+    };
+    // Selectors may resolve to keys outside the directory; rows are kept
+    // within its range, so these stay as the bounds.
+    let (lower, upper) = (begin.clone(), end.clone());
+    let mut begin_sel = scan.begin_sel.as_ref().map(|s| s.resolve(&dir_prefix));
+    let end_sel = scan.end_sel.as_ref().map(|s| s.resolve(&dir_prefix));
+    // A continued scan resumes after its own last key, which is past `after`.
+    if let Some(after) = resume.as_ref().or(scan.after.as_ref()) {
+        let mut start = dir_prefix.clone();
+        start.extend_from_slice(after);
+        start.push(0x00);
+        begin = begin.max(start);
+        begin_sel = None;
+    }
+    if let (None, Some(max), None) = (scan.limit, scan.max_scan_bytes, &resume) {
+        if begin_sel.is_none() && end_sel.is_none() {
+            check_scan_size(trx, &begin, &end, max).await?;
+        }
+    }
+    if scan.progress_bar && scan.limit.is_none() && state.progress.borrow().bar.is_none() {
+        let estimate = trx.get_estimated_range_size_bytes(&begin, &end).await?;
+        state.progress.borrow_mut().bar = Some(ProgressBar::start(estimate));
+    }
 
-            // Directories
-            println!("{}", "Directories:".bold());
-            let items = dl.list(&trx, &path).await?;
-            if items.is_empty() {
-                println!("(none)");
-            }
-            for name in items {
-                let display = format!("{}/", name);
-                println!("{}", display.blue().bold());
+    let selected = begin_sel.is_some() || end_sel.is_some();
+    // Fetch one extra row to tell whether the limit cut the scan short.
+    let mut opt: RangeOption = match (begin_sel, end_sel) {
+        (None, None) => (begin, end).into(),
+        (b, e) => (
+            b.unwrap_or_else(|| KeySelector::first_greater_or_equal(begin)),
+            e.unwrap_or_else(|| KeySelector::first_greater_or_equal(end)),
+        )
+            .into(),
+    };
+    opt.limit = match scan.tail {
+        Some(n) => Some(n),
+        None => scan.limit.map(|n| n - printed + 1),
+    };
+    // Keys a selector lands on outside the directory are skipped below, so
+    // they mustn't count toward the limit.
+    if selected {
+        opt.limit = None;
+    }
+    opt.reverse = scan.tail.is_some();
+    if let Some(mode) = scan.streaming {
+        opt.mode = mode;
+    } else if scan.limit.is_none() {
+        opt.mode = StreamingMode::WantAll;
+    }
+
+    let mut i = printed;
+    let mut stream = trx.get_ranges_keyvalues(opt, read.snapshot).boxed_local();
+    // --tail reads backwards from the end; the rows are printed in key order.
+    if let Some(n) = scan.tail {
+        let mut rows: Vec<_> = stream.try_collect().await?;
+        // With selectors the read isn't limited; keep the last n in range.
+        rows.retain(|kv| kv.key() >= lower.as_slice() && kv.key() < upper.as_slice());
+        rows.truncate(n);
+        rows.reverse();
+        stream = futures_util::stream::iter(rows.into_iter().map(Ok)).boxed_local();
+    }
+    if let Some(last) = resume.as_ref() {
+        let version = trx.get_read_version().await?;
+        state
+            .sink
+            .borrow_mut()
+            .resumed(last, version)
+            .map_err(io_err)?;
+    } else if attempt == 1 {
+        state
+            .sink
+            .borrow_mut()
+            .start(path, &dir_prefix)
+            .map_err(io_err)?;
+    }
+    while let Some(item) = stream.try_next().await? {
+        if item.key() < lower.as_slice() {
+            continue;
+        }
+        if item.key() >= upper.as_slice() {
+            break;
+        }
+        if scan.limit.is_some_and(|n| i >= n) {
+            return Ok(Some(
+                state.progress.borrow().last.clone().unwrap_or_default(),
+            ));
+        }
+        i += 1;
+        let key = item.key();
+        let val = item.value();
+
+        let rest = match scan.prefix.as_ref() {
+            Some(pfx) if scan.subspace && !fmt.absolute_keys => {
+                Some(&key[dir_prefix.len() + pfx.len()..])
             }
+            _ => None,
+        };
+        let projected = project_key(
+            rest.unwrap_or(&key[dir_prefix.len()..]),
+            scan.fields.as_deref(),
+            scan.schema.as_deref().unwrap_or_default(),
+            &fmt,
+        );
+        let key_fmt = match (projected, rest) {
+            (Some(text), _) => text,
+            (None, Some(rest)) => format_subspace_key(rest, &fmt),
+            (None, None) => format_key(&dir, key, &fmt),
+        };
+        let problem = if scan.verify_roundtrip {
+            roundtrip_problem(&key[dir_prefix.len()..], fmt.max_key_bytes)
+        } else {
+            None
+        };
+        let mut p = state.progress.borrow_mut();
+        if let Some(bar) = p.bar.as_mut() {
+            bar.add(key.len() + val.len());
+        }
+        if problem.is_some() {
+            p.mismatches += 1;
+        }
+        state
+            .sink
+            .borrow_mut()
+            .row(ScanRow {
+                index: i,
+                key: key[dir_prefix.len()..].to_vec(),
+                value: val.to_vec(),
+                key_text: key_fmt,
+                problem,
+            })
+            .map_err(io_err)?;
+        p.printed = i;
+        p.last = Some(key[dir_prefix.len()..].to_vec());
+        p.key_bytes += key.len();
+        p.value_bytes += val.len();
+    }
+    Ok(None)
+}
 
-            // Keys (first N). Skip at root (no content keys at the directory layer root).
-            if path.is_empty() {
-                return Ok(());
+#[derive(Clone, Debug)]
+pub struct RawScanOptions {
+    pub begin: Vec<u8>,
+    // Defaults to the end of the normal keyspace (\xff), or of the system keyspace with `system`.
+    pub end: Option<Vec<u8>>,
+    pub limit: Option<usize>,
+    // Allow reads in \xff.. (TransactionOption::ReadSystemKeys).
+    pub system: bool,
+    pub streaming: Option<StreamingMode>,
+}
+
+// Scans absolute keys in [begin, end) without the directory layer; keys are
+// shown as raw bytes since there is no subspace to unpack them against.
+pub async fn raw_scan<W: Write>(
+    db: &Client,
+    out: &mut W,
+    raw: RawScanOptions,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<()> {
+    check_limit(raw.limit)?;
+    let end = raw.end.clone().unwrap_or_else(|| {
+        if raw.system {
+            b"\xff\xff".to_vec()
+        } else {
+            b"\xff".to_vec()
+        }
+    });
+    if raw.begin >= end {
+        return Err(anyhow!(
+            "begin {} must sort before end {}",
+            format_bytes(&raw.begin, fmt.max_key_bytes),
+            format_bytes(&end, fmt.max_key_bytes)
+        ));
+    }
+    let out = RefCell::new(out);
+    run_read(db, read, |trx, _| {
+        let raw = raw.clone();
+        let end = end.clone();
+        let out = &out;
+        async move {
+            if raw.system {
+                trx.set_option(TransactionOption::ReadSystemKeys)?;
+            }
+            writeln!(
+                out.borrow_mut(),
+                "-- raw scan [{}, {}) ({}) --",
+                format_bytes(&raw.begin, fmt.max_key_bytes).yellow(),
+                format_bytes(&end, fmt.max_key_bytes).yellow(),
+                raw.limit
+                    .map(|n| format!("limit {n}"))
+                    .unwrap_or_else(|| "all".to_string())
+            )
+            .map_err(io_err)?;
+            let mut opt: RangeOption = (raw.begin.clone(), end).into();
+            opt.limit = raw.limit.map(|n| n + 1);
+            if let Some(mode) = raw.streaming {
+                opt.mode = mode;
+            } else if raw.limit.is_none() {
+                opt.mode = StreamingMode::WantAll;
             }
-            println!("{}", format!("Keys (first {SAMPLE}):").bold());
-            let dir = dir_for_path(&trx, &path).await?;
-            let (begin, end) = dir.range()?;
-            let mut opt: RangeOption = (begin, end).into();
-            opt.limit = Some(SAMPLE + 1);
             let mut i = 0usize;
-            let mut more = false;
-            let mut stream = trx.get_ranges_keyvalues(opt, true);
+            let mut last: Vec<u8> = Vec::new();
+            let mut stream = trx.get_ranges_keyvalues(opt, read.snapshot);
             while let Some(item) = stream.try_next().await? {
-                i += 1;
-                if i > SAMPLE {
-                    more = true;
+                if raw.limit.is_some_and(|n| i >= n) {
+                    last.push(0x00);
+                    writeln!(
+                        out.borrow_mut(),
+                        "{} {}",
+                        "… more; continue from begin".dimmed(),
+                        format!("'{}'", key_literal(&last)).dimmed()
+                    )
+                    .map_err(io_err)?;
                     break;
                 }
-                let key = item.key();
-                let val = item.value();
-
-                let key_fmt = match dir.unpack::<Element>(key) {
-                    Ok(Ok(el)) => format_element(&el),
-                    _ => format_bytes(key),
-                };
-                let val_fmt = match Element::unpack_root(val) {
-                    Ok(el) => format_element(&el),
-                    Err(_) => try_utf8_or_bytes(val),
+                i += 1;
+                let key_fmt = if fmt.hex_keys {
+                    format_hexdump(item.key(), fmt.max_key_bytes, 6)
+                } else {
+                    format_raw(item.key(), &fmt, fmt.max_key_bytes)
                 };
-                println!(
+                let row = format!(
                     "{} {} {} {}",
                     format!("{i:>4}.").dimmed(),
                     key_fmt.cyan(),
                     "=>".dimmed(),
-                    val_fmt.green()
+                    paint_value(item.value(), &fmt)
                 );
+                writeln!(out.borrow_mut(), "{}", fit_width(row, fmt.width)).map_err(io_err)?;
+                last = item.key().to_vec();
             }
-            if i == 0 {
-                println!("(none)");
-            }
-            if more {
-                println!(
-                    "{} {}",
-                    "…".dimmed(),
-                    "use 'scan [limit]' to see more".dimmed()
-                );
-            }
-
             Ok(())
         }
     })
     .await
-    .map_err(|e| anyhow!("{:?}", e))
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+async fn read_value(
+    db: &Client,
+    trx: &Transaction,
+    path: &[String],
+    key: &[u8],
+    read: ReadOptions,
+) -> Result<Option<Vec<u8>>, FdbBindingError> {
+    let dir = db.dir_for_path(trx, path, None).await?;
+    let mut full_key = content_prefix(&dir)?.to_vec();
+    full_key.extend_from_slice(key);
+    Ok(trx.get(&full_key, read.snapshot).await?.map(|v| v.to_vec()))
+}
+
+pub async fn get_value<W: Write>(
+    src: Source<'_>,
+    out: &mut W,
+    path: Vec<String>,
+    key: Vec<u8>,
+    read: ReadOptions,
+    fmt: FormatOptions,
+) -> Result<()> {
+    let value = match src {
+        Source::Db(db) => {
+            run_read(db, read, |trx, _| {
+                let path = path.clone();
+                let key = key.clone();
+                async move { read_value(db, &trx, &path, &key, read).await }
+            })
+            .await
+        }
+        Source::Trx(db, trx) => read_value(db, trx, &path, &key, read).await,
+    }
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    let value = value.ok_or_else(|| {
+        anyhow!(
+            "key {} not found in {}",
+            format_bytes(&key, fmt.max_key_bytes),
+            display_path(&path)
+        )
+    })?;
+    writeln!(out, "{}", paint_value(&value, &fmt))?;
+    Ok(())
 }
 
-pub async fn scan_path(
-    db: &foundationdb::Database,
+pub async fn watch_key<W: Write>(
+    db: &Client,
+    out: &mut W,
     path: Vec<String>,
-    limit: usize,
-    prefix: Option<Vec<u8>>,
-    raw_keys: bool,
+    key: Vec<u8>,
 ) -> Result<()> {
-    db.run(|trx, _| {
+    // Read the current value and register the watch in one transaction; the
+    // watch stays armed after commit until the value changes.
+    let (full_key, current, watch) = run(db, |trx, _| {
         let path = path.clone();
-        let prefix = prefix.clone();
+        let key = key.clone();
         async move {
-            let dir = dir_for_path(&trx, &path).await?;
-            let (begin, end) = if let Some(pfx) = prefix.as_ref() {
-                let mut start = dir.bytes()?.to_vec();
-                start.extend_from_slice(pfx);
-                let end = strinc(start.clone());
-                (start, end)
-            } else {
-                dir.range()?
-            };
+            let dir = db.dir_for_path(&trx, &path, None).await?;
+            let mut full_key = content_prefix(&dir)?.to_vec();
+            full_key.extend_from_slice(&key);
+            let current = trx.get(&full_key, false).await?.map(|v| v.to_vec());
+            let watch = trx.watch(&full_key);
+            Ok((full_key, current, watch))
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
 
-            let mut opt: RangeOption = (begin, end).into();
-            opt.limit = Some(limit);
+    writeln!(
+        out,
+        "-- watching {} in {} (Ctrl-C to cancel) --",
+        format_bytes(&key, DEFAULT_MAX_BYTES).cyan(),
+        display_path(&path).yellow()
+    )?;
+    writeln!(
+        out,
+        "{} {}",
+        "current:".dimmed(),
+        current
+            .as_deref()
+            .map(|v| format_value(v, &FormatOptions::default()))
+            .unwrap_or_else(|| "(not set)".to_string())
+            .green()
+    )?;
+    tokio::select! {
+        res = watch => res.map_err(|e| anyhow!(describe_error(e)))?,
+        _ = tokio::signal::ctrl_c() => {
+            writeln!(out, "{}", "watch cancelled".dimmed())?;
+            return Ok(());
+        }
+    }
 
-            let mut i = 0usize;
-            let mut stream = trx.get_ranges_keyvalues(opt, true);
-            println!(
-                "-- scanning {} (limit {limit}{}) --",
-                display_path(&path).yellow(),
-                prefix
-                    .as_ref()
-                    .map(|p| format!(", prefix {}", format_bytes(p)))
-                    .unwrap_or_default()
-            );
-            while let Some(item) = stream.try_next().await? {
-                i += 1;
-                let key = item.key();
-                let val = item.value();
+    let updated = run(db, |trx, _| {
+        let full_key = full_key.clone();
+        async move { Ok(trx.get(&full_key, false).await?.map(|v| v.to_vec())) }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    writeln!(
+        out,
+        "{} {}",
+        "changed:".dimmed(),
+        updated
+            .as_deref()
+            .map(|v| format_value(v, &FormatOptions::default()))
+            .unwrap_or_else(|| "(cleared)".to_string())
+            .green()
+    )?;
+    Ok(())
+}
 
-                let key_fmt = if raw_keys {
-                    format_bytes(key)
-                } else {
-                    match dir.unpack::<Element>(key) {
-                        Ok(Ok(el)) => format_element(&el),
-                        _ => format_bytes(key),
-                    }
-                };
+// How long each watch-dir round waits before checking for writes; well inside
+// the 5s a read version stays valid.
+const WATCH_DIR_INTERVAL: Duration = Duration::from_secs(1);
 
-                let val_fmt = match Element::unpack_root(val) {
-                    Ok(el) => format_element(&el),
-                    Err(_) => try_utf8_or_bytes(val),
-                };
+// FoundationDB watches cover a single key, so a directory is watched through
+// conflict checking instead. Each round reads nothing: it declares the
+// directory's range as read (at the previous round's commit version, so no
+// write falls between rounds) and commits with a write conflict on the bare
+// prefix key, which lies outside the range so other watchers are not
+// disturbed. The commit fails with not_committed exactly when some key in the
+// range was written since the read version.
+pub async fn watch_dir<W: Write>(db: &Client, out: &mut W, path: Vec<String>) -> Result<()> {
+    let (prefix, (begin, end)) = run(db, |trx, _| {
+        let path = path.clone();
+        async move {
+            let dir = db.dir_for_path(&trx, &path, None).await?;
+            Ok((content_prefix(&dir)?.to_vec(), dir.range()?))
+        }
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    let mut prefix_end = prefix.clone();
+    prefix_end.push(0x00);
+    let fail = |e: FdbError| anyhow!(describe_error(e));
 
-                println!(
-                    "{} {} {} {}",
-                    format!("{i:>4}.").dimmed(),
-                    key_fmt.cyan(),
-                    "=>".dimmed(),
-                    val_fmt.green()
-                );
+    writeln!(
+        out,
+        "-- watching {} for changes (Ctrl-C to cancel) --",
+        display_path(&path).yellow()
+    )?;
+    let mut since = None;
+    loop {
+        let trx = db.create_trx().map_err(fail)?;
+        db.apply_tag(&trx).map_err(fail)?;
+        match since {
+            Some(version) => trx.set_read_version(version),
+            None => {
+                trx.get_read_version().await.map_err(fail)?;
             }
-            Ok(())
         }
+        trx.add_conflict_range(&begin, &end, ConflictRangeType::Read)
+            .map_err(fail)?;
+        trx.add_conflict_range(&prefix, &prefix_end, ConflictRangeType::Write)
+            .map_err(fail)?;
+        tokio::select! {
+            _ = tokio::time::sleep(WATCH_DIR_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => {
+                writeln!(out, "{}", "watch cancelled".dimmed())?;
+                return Ok(());
+            }
+        }
+        match trx.commit().await {
+            Ok(committed) => since = Some(committed.committed_version().map_err(fail)?),
+            // not_committed: a write under the directory conflicted.
+            Err(e) if e.code() == 1020 => {
+                writeln!(
+                    out,
+                    "{} {} changed",
+                    clock_time().dimmed(),
+                    display_path(&path).yellow()
+                )?;
+                since = None;
+            }
+            Err(e) if e.is_retryable() => {
+                since = None;
+                e.on_error().await.map_err(fail)?;
+            }
+            Err(e) => return Err(fail(*e)),
+        }
+    }
+}
+
+// Wall-clock time of day as HH:MM:SS UTC.
+fn clock_time() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+pub async fn dir_exists(db: &Client, path: &[String]) -> Result<bool> {
+    run(db, |trx, _| async move {
+        Ok(db.directory_layer().exists(&trx, path).await?)
     })
     .await
-    .map_err(|e| anyhow!("{:?}", e))
+    .map_err(|e| anyhow!(describe_error(e)))
 }
 
-pub fn display_path(path: &[String]) -> String {
+// `pwd --layers`: every directory from the top of `path` down to it, each with
+// its layer, so partition boundaries along the way stand out.
+pub async fn print_layers<W: Write>(db: &Client, out: &mut W, path: &[String]) -> Result<()> {
+    let layers = run(db, |trx, _| async move {
+        let mut layers = Vec::new();
+        for depth in 1..=path.len() {
+            let dir = db.dir_for_path(&trx, &path[..depth], None).await?;
+            layers.push(dir.get_layer().to_vec());
+        }
+        Ok(layers)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
     if path.is_empty() {
-        "/".to_string()
-    } else {
-        format!("/{}", path.join("/"))
+        writeln!(out, "{}", "/".yellow())?;
+        return Ok(());
+    }
+    let width = display_path(path).chars().count();
+    for (depth, layer) in layers.iter().enumerate() {
+        let name = display_path(&path[..=depth]);
+        let layer = match layer.as_slice() {
+            [] => "-".to_string(),
+            l => try_utf8_or_bytes(l, &FormatOptions::default(), DEFAULT_MAX_BYTES),
+        };
+        writeln!(
+            out,
+            "{}  {}",
+            format!("{name:<width$}").yellow(),
+            layer.magenta()
+        )?;
     }
+    Ok(())
 }
 
-pub fn format_element(el: &Element<'_>) -> String {
-    match el {
-        Element::Nil => "nil".to_string(),
-        Element::Bytes(b) => format!("{}", b),
-        Element::String(s) => format!("\"{}\"", s),
-        Element::Tuple(items) => {
-            let mut parts = Vec::with_capacity(items.len());
-            for it in items {
-                parts.push(format_element(it));
+// `find-prefix`: the directory whose prefix `key` starts with. Each node in
+// the directory layer's metadata maps (0, name) to a subdirectory's prefix,
+// so this walks down from the root reading one node's subdirectories at a
+// time, following partitions into their own node space.
+pub async fn find_prefix<W: Write>(db: &Client, out: &mut W, key: &[u8]) -> Result<()> {
+    let root_prefix = db.node_prefix.as_slice();
+    if key.starts_with(root_prefix) {
+        writeln!(
+            out,
+            "{} is directory layer metadata (node prefix {})",
+            format_bytes(key, DEFAULT_MAX_BYTES),
+            format_bytes(root_prefix, DEFAULT_MAX_BYTES)
+        )?;
+        return Ok(());
+    }
+    let found = run(db, |trx, _| async move {
+        let mut nodes = Subspace::from_bytes(root_prefix);
+        let mut node = nodes.subspace(&root_prefix);
+        let mut path = Vec::new();
+        let mut owner = None;
+        loop {
+            let subdirs = node.subspace(&(0i64,));
+            let mut opt = RangeOption::from(subdirs.range());
+            opt.mode = StreamingMode::WantAll;
+            let mut stream = trx.get_ranges_keyvalues(opt, false);
+            let mut child = None;
+            while let Some(kv) = stream.try_next().await? {
+                if key.starts_with(kv.value()) {
+                    let name: String = subdirs
+                        .unpack(kv.key())
+                        .map_err(FdbBindingError::PackError)?;
+                    child = Some((name, kv.value().to_vec()));
+                    break;
+                }
+            }
+            let Some((name, prefix)) = child else {
+                break;
+            };
+            path.push(name);
+            node = nodes.subspace(&prefix);
+            let layer = trx
+                .get(&node.pack(&&b"layer"[..]), false)
+                .await?
+                .map(|v| v.to_vec())
+                .unwrap_or_default();
+            // A partition's subdirectories live in a directory layer of its own.
+            if layer == b"partition" {
+                nodes = Subspace::from_bytes([prefix.as_slice(), b"\xfe"].concat());
+                node = nodes.subspace(&nodes.bytes());
             }
-            format!("({})", parts.join(", "))
+            owner = Some((prefix, layer));
         }
-        Element::Int(i) => format!("{i}"),
-        Element::Float(f) => format!("{}f32", f),
-        Element::Double(d) => format!("{}f64", d),
-        Element::Bool(b) => format!("{b}"),
-        Element::Uuid(u) => format!("uuid:{u}"),
-        Element::Versionstamp(vs) => format!("versionstamp:{}", hex::encode(vs.as_bytes())),
-    }
+        Ok(owner.map(|(prefix, layer)| (path, prefix, layer)))
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    let Some((path, prefix, layer)) = found else {
+        return Err(anyhow!(
+            "no directory owns {}",
+            format_bytes(key, DEFAULT_MAX_BYTES)
+        ));
+    };
+    let layer = match layer.as_slice() {
+        [] => "-".to_string(),
+        l => try_utf8_or_bytes(l, &FormatOptions::default(), DEFAULT_MAX_BYTES),
+    };
+    writeln!(
+        out,
+        "{}  {} {}  {} {}",
+        display_path(&path).yellow(),
+        "prefix".dimmed(),
+        format_bytes(&prefix, DEFAULT_MAX_BYTES),
+        "layer".dimmed(),
+        layer.magenta()
+    )?;
+    Ok(())
 }
 
-pub fn try_utf8_or_bytes(b: &[u8]) -> String {
-    match std::str::from_utf8(b) {
-        Ok(s)
-            if s.chars()
-                .all(|c| !c.is_control() || c == '\n' || c == '\r' || c == '\t') =>
-        {
-            format!("\"{}\"", s)
-        }
-        _ => format_bytes(b),
-    }
+pub async fn list_dirs(db: &Client, path: &[String]) -> Result<Vec<String>> {
+    run(db, |trx, _| async move {
+        let dl = db.directory_layer();
+        Ok(dl.list(&trx, path).await?)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
 }
 
-pub fn format_bytes(b: &[u8]) -> String {
-    const MAX: usize = 64;
-    let mut out = String::new();
-    out.push_str("b\"");
-    for (idx, byte) in b.iter().enumerate() {
-        if idx >= MAX {
-            out.push('…');
-            break;
-        }
-        if *byte == b'\\' {
-            out.push_str(r"\\");
-        } else if byte.is_ascii_alphanumeric() || *byte == b'-' || *byte == b'_' {
-            out.push(*byte as char);
-        } else {
-            out.push_str(&format!("\\x{:02x}", byte));
-        }
-    }
-    out.push('"');
-    out
-}
-
-pub fn parse_bytes_literal(s: &str) -> Result<Vec<u8>> {
-    let mut out = Vec::with_capacity(s.len());
-    let bytes = s.as_bytes();
-    let mut i = 0;
-    while i < bytes.len() {
-        let c = bytes[i];
-        if c == b'\\' && i + 1 < bytes.len() {
-            let n = bytes[i + 1];
-            match n {
-                b'x' | b'X' => {
-                    if i + 3 >= bytes.len() {
-                        return Err(anyhow!("incomplete hex escape"));
-                    }
-                    let h1 = bytes[i + 2] as char;
-                    let h2 = bytes[i + 3] as char;
-                    let v = (hex_val(h1)? << 4) | hex_val(h2)?;
-                    out.push(v);
-                    i += 4;
-                    continue;
-                }
-                b'n' => {
-                    out.push(b'\n');
-                    i += 2;
-                    continue;
-                }
-                b'r' => {
-                    out.push(b'\r');
-                    i += 2;
-                    continue;
-                }
-                b't' => {
-                    out.push(b'\t');
-                    i += 2;
-                    continue;
-                }
-                b'\\' => {
-                    out.push(b'\\');
-                    i += 2;
-                    continue;
-                }
-                b'"' => {
-                    out.push(b'"');
-                    i += 2;
-                    continue;
+// Distinct values of the tuple element that follows `prefix` (packed, relative
+// to the directory) among the directory's keys, in key order, each paired with
+// whether the key it came from has further elements. After each hit the read
+// jumps past every key sharing that element, so `max` values cost at most `max`
+// one-row reads. Keys that are not tuples are stepped over.
+pub async fn sample_next_elements(
+    db: &Client,
+    path: &[String],
+    prefix: &[u8],
+    max: usize,
+) -> Result<Vec<(String, bool)>> {
+    run(db, |trx, _| async move {
+        let dir = db.dir_for_path(&trx, path, None).await?;
+        let mut start = content_prefix(&dir)?.to_vec();
+        start.extend_from_slice(prefix);
+        let end = strinc(start.clone())?;
+        let mut begin = start.clone();
+        let mut found = Vec::new();
+        while found.len() < max {
+            let mut opt: RangeOption = (begin.clone(), end.clone()).into();
+            opt.limit = Some(1);
+            let rows = trx.get_range(&opt, 1, true).await?;
+            let Some(row) = rows.first() else { break };
+            let rest = &row.key()[start.len()..];
+            match Element::unpack_root(rest) {
+                Ok(Element::Tuple(items)) if !items.is_empty() => {
+                    found.push((
+                        format_element(&items[0], &FormatOptions::default()),
+                        items.len() > 1,
+                    ));
+                    let mut next = start.clone();
+                    next.extend_from_slice(&foundationdb::tuple::pack(&items[..1].to_vec()));
+                    begin = strinc(next)?;
                 }
                 _ => {
-                    out.push(n);
-                    i += 2;
-                    continue;
+                    begin = row.key().to_vec();
+                    begin.push(0x00);
                 }
             }
         }
-        out.push(c);
-        i += 1;
+        Ok(found)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+pub async fn make_dir(db: &Client, path: &[String], layer: Option<&[u8]>) -> Result<()> {
+    if path.is_empty() {
+        return Err(anyhow!("the root directory always exists"));
+    }
+    run(db, |trx, _| async move {
+        let dl = db.directory_layer();
+        dl.create(&trx, path, None, layer).await?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Removes the directory, its subdirectories, and all of their keys.
+pub async fn remove_dir(db: &Client, path: &[String]) -> Result<()> {
+    if path.is_empty() {
+        return Err(anyhow!("refusing to remove the root directory"));
     }
-    Ok(out)
+    let removed = run(db, |trx, _| async move {
+        let dl = db.directory_layer();
+        Ok(dl.remove_if_exists(&trx, path).await?)
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    if !removed {
+        return Err(anyhow!("no such directory: {}", display_path(path)));
+    }
+    Ok(())
 }
 
-fn hex_val(c: char) -> Result<u8> {
-    match c {
-        '0'..='9' => Ok((c as u8) - b'0'),
-        'a'..='f' => Ok((c as u8) - b'a' + 10),
-        'A'..='F' => Ok((c as u8) - b'A' + 10),
-        _ => Err(anyhow!("invalid hex digit")),
+// Dry run of remove_dir: every directory that would go and the key range each one clears.
+pub async fn plan_remove<W: Write>(db: &Client, out: &mut W, path: &[String]) -> Result<()> {
+    if path.is_empty() {
+        return Err(anyhow!("refusing to remove the root directory"));
     }
+    let mut count = 0usize;
+    let mut stack = vec![path.to_vec()];
+    while let Some(path) = stack.pop() {
+        let range = run(db, |trx, _| {
+            let path = path.clone();
+            async move {
+                let dir = db.dir_for_path(&trx, &path, None).await?;
+                // Partitions hold no keys of their own.
+                Ok(dir.bytes().is_ok().then(|| dir.range()).transpose()?)
+            }
+        })
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+        match range {
+            Some((begin, end)) => writeln!(
+                out,
+                "would remove {} and clear [{}, {})",
+                display_path(&path).yellow(),
+                format_bytes(&begin, DEFAULT_MAX_BYTES),
+                format_bytes(&end, DEFAULT_MAX_BYTES)
+            )?,
+            None => writeln!(
+                out,
+                "would remove partition {}",
+                display_path(&path).yellow()
+            )?,
+        }
+        count += 1;
+        for name in list_dirs(db, &path).await?.into_iter().rev() {
+            let mut child = path.clone();
+            child.push(name);
+            stack.push(child);
+        }
+    }
+    writeln!(
+        out,
+        "{count} directories would be removed; nothing changed (dry run)"
+    )?;
+    Ok(())
+}
+
+fn check_move(src: &[String], dst: &[String]) -> Result<()> {
+    if src.is_empty() || dst.is_empty() {
+        return Err(anyhow!("cannot move the root directory"));
+    }
+    if dst.starts_with(src) {
+        return Err(anyhow!("cannot move {} into itself", display_path(src)));
+    }
+    Ok(())
+}
+
+// Dry run of move_dir: the move runs in a transaction that is dropped without
+// committing, so the directory layer's own checks still apply.
+pub async fn plan_move<W: Write>(
+    db: &Client,
+    out: &mut W,
+    src: &[String],
+    dst: &[String],
+) -> Result<()> {
+    check_move(src, dst)?;
+    let trx = db.create_trx().map_err(|e| anyhow!(describe_error(e)))?;
+    db.apply_tag(&trx).map_err(|e| anyhow!(describe_error(e)))?;
+    db.directory_layer()
+        .move_to(&trx, src, dst)
+        .await
+        .map_err(|e| anyhow!(describe_error(e)))?;
+    writeln!(
+        out,
+        "would move {} to {} (keys keep their prefix); nothing changed (dry run)",
+        display_path(src).yellow(),
+        display_path(dst).yellow()
+    )?;
+    Ok(())
+}
+
+// Renames a directory; its prefix and keys are unchanged.
+pub async fn move_dir(db: &Client, src: &[String], dst: &[String]) -> Result<()> {
+    check_move(src, dst)?;
+    run(db, |trx, _| async move {
+        let dl = db.directory_layer();
+        dl.move_to(&trx, src, dst).await?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Absolute [begin, end) of the keys under `prefix` (relative to the directory).
+async fn prefix_range(
+    db: &Client,
+    trx: &Transaction,
+    path: &[String],
+    prefix: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), FdbBindingError> {
+    let dir = db.dir_for_path(trx, path, None).await?;
+    let mut begin = content_prefix(&dir)?.to_vec();
+    begin.extend_from_slice(prefix);
+    let end = strinc(begin.clone())?;
+    Ok((begin, end))
+}
+
+// An empty prefix would clear the whole directory; that is `rm`'s job.
+fn check_clear_prefix(prefix: &[u8]) -> Result<()> {
+    if prefix.is_empty() {
+        return Err(anyhow!(
+            "prefix must not be empty (rm removes a whole directory)"
+        ));
+    }
+    Ok(())
+}
+
+pub async fn plan_clear_prefix<W: Write>(
+    db: &Client,
+    out: &mut W,
+    path: &[String],
+    prefix: &[u8],
+) -> Result<()> {
+    check_clear_prefix(prefix)?;
+    let (begin, end, keys, bytes) = run(db, |trx, _| async move {
+        let (begin, end) = prefix_range(db, &trx, path, prefix).await?;
+        let mut opt: RangeOption = (begin.clone(), end.clone()).into();
+        opt.limit = Some(COUNT_CAP + 1);
+        opt.mode = StreamingMode::WantAll;
+        let mut keys = 0usize;
+        let mut stream = trx.get_ranges_keyvalues(opt, true);
+        while stream.try_next().await?.is_some() {
+            keys += 1;
+        }
+        let bytes = trx.get_estimated_range_size_bytes(&begin, &end).await?;
+        Ok((begin, end, keys, bytes))
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))?;
+    writeln!(
+        out,
+        "would clear [{}, {}) in {}",
+        format_bytes(&begin, DEFAULT_MAX_BYTES),
+        format_bytes(&end, DEFAULT_MAX_BYTES),
+        display_path(path).yellow()
+    )?;
+    let keys = if keys > COUNT_CAP {
+        format!("{COUNT_CAP}+")
+    } else {
+        keys.to_string()
+    };
+    writeln!(
+        out,
+        "{keys} keys, about {} (sampled estimate); nothing changed (dry run)",
+        crate::status::format_size(bytes as f64)
+    )?;
+    Ok(())
 }
 
-fn strinc(mut key: Vec<u8>) -> Vec<u8> {
+pub async fn clear_prefix(db: &Client, path: &[String], prefix: &[u8]) -> Result<()> {
+    check_clear_prefix(prefix)?;
+    run(db, |trx, _| async move {
+        let (begin, end) = prefix_range(db, &trx, path, prefix).await?;
+        trx.clear_range(&begin, &end);
+        Ok(())
+    })
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Gate shared by destructive commands. `yes` proceeds; otherwise ask, unless
+// there is no one to ask (scripts, piped stdin), where the command is refused.
+pub fn approve(cmd: &str, prompt: &str, yes: bool, interactive: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    if !interactive {
+        return Err(anyhow!("{cmd} needs --yes when not interactive"));
+    }
+    if confirm(prompt)? {
+        return Ok(true);
+    }
+    println!("Aborted.");
+    Ok(false)
+}
+
+// Asks on stdin; anything but y/yes declines.
+pub fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+pub async fn find_dirs<W: Write>(
+    db: &Client,
+    out: &mut W,
+    root: Vec<String>,
+    pattern: &glob::Pattern,
+    max_depth: Option<usize>,
+) -> Result<usize> {
+    // Depth-first, one transaction per listing so large trees don't hit the 5s limit.
+    // The starting directory itself is not matched.
+    let mut found = 0usize;
+    let mut stack = vec![(root, 0usize)];
+    while let Some((path, depth)) = stack.pop() {
+        if depth > 0 && pattern.matches(path.last().map(|s| s.as_str()).unwrap_or_default()) {
+            found += 1;
+            writeln!(out, "{}", display_path(&path).blue().bold())?;
+        }
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        // Push in reverse so children pop in list (lexicographic) order.
+        for name in list_dirs(db, &path).await?.into_iter().rev() {
+            let mut child = path.clone();
+            child.push(name);
+            stack.push((child, depth + 1));
+        }
+    }
+    Ok(found)
+}
+
+pub async fn read_version(db: &Client) -> Result<i64> {
+    run(
+        db,
+        |trx, _| async move { Ok(trx.get_read_version().await?) },
+    )
+    .await
+    .map_err(|e| anyhow!(describe_error(e)))
+}
+
+// Fails before a whole-range read when the cluster's sampled estimate of
+// [begin, end) is over `max` bytes. The estimate is coarse for small ranges,
+// but the point is to catch gigabytes, not to be exact.
+pub async fn check_scan_size(
+    trx: &Transaction,
+    begin: &[u8],
+    end: &[u8],
+    max: u64,
+) -> Result<(), FdbBindingError> {
+    let estimate = trx.get_estimated_range_size_bytes(begin, end).await?;
+    if estimate.max(0) as u64 > max {
+        let size = crate::status::format_size;
+        return Err(FdbBindingError::CustomError(
+            anyhow!(
+                "range holds about {} (estimated), over the max-scan-bytes limit of {}; narrow it with --limit or --prefix, or raise the limit",
+                size(estimate as f64),
+                size(max as f64)
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+// First key past every key starting with `key`. An empty or all-\xff key has
+// none; returning an empty end there would make [key, end) silently empty.
+fn strinc(mut key: Vec<u8>) -> Result<Vec<u8>, FdbBindingError> {
     for i in (0..key.len()).rev() {
         if key[i] != 0xff {
             key[i] += 1;
             key.truncate(i + 1);
-            return key;
+            return Ok(key);
+        }
+    }
+    Err(FdbBindingError::CustomError(
+        format!(
+            "no key sorts after every key starting with {}",
+            format_bytes(&key, DEFAULT_MAX_BYTES)
+        )
+        .into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_at(version: Option<i64>) -> ReadOptions {
+        ReadOptions {
+            snapshot: true,
+            read_version: version,
         }
     }
-    Vec::new()
+
+    #[test]
+    fn pinned_version_errors_end_the_run() {
+        for code in [1007, 1009] {
+            let errors: [FdbBindingError; 2] = [
+                FdbError::from_code(code).into(),
+                // What the directory open returns.
+                DirectoryError::FdbError(FdbError::from_code(code)).into(),
+            ];
+            for e in errors {
+                let e = pinned_version_error(e, read_at(Some(42)));
+                // run() only retries errors that still carry an FdbError.
+                assert!(e.get_fdb_error().is_none(), "code {code} would be retried");
+                let msg = describe_error(e);
+                assert!(msg.contains("pinned read version 42"), "{msg}");
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_scan_too_old_gets_advice() {
+        let e = pinned_version_error(FdbError::from_code(1007).into(), read_at(Some(42)));
+        assert!(describe_scan_error(e).contains("--limit"));
+        let e = pinned_version_error(FdbError::from_code(1009).into(), read_at(Some(42)));
+        assert!(!describe_scan_error(e).contains("--limit"));
+    }
+
+    #[test]
+    fn other_errors_stay_retryable() {
+        let e = pinned_version_error(FdbError::from_code(1007).into(), read_at(None));
+        assert_eq!(e.get_fdb_error().map(FdbError::code), Some(1007));
+        let e = pinned_version_error(FdbError::from_code(1020).into(), read_at(Some(42)));
+        assert_eq!(e.get_fdb_error().map(FdbError::code), Some(1020));
+    }
+
+    #[test]
+    fn zero_limit_is_not_unlimited() {
+        let err = check_limit(Some(0)).unwrap_err().to_string();
+        assert!(err.contains("at least 1"), "{err}");
+        assert!(check_limit(Some(1)).is_ok());
+        // Only --all asks for everything.
+        assert!(check_limit(None).is_ok());
+    }
+
+    #[test]
+    fn low_read_priority_needs_api_730() {
+        let options = |low_read_priority, api_version| ClientOptions {
+            low_read_priority,
+            api_version: Some(api_version),
+            ..ClientOptions::default()
+        };
+        let err = options(true, 710).check().unwrap_err().to_string();
+        assert!(err.contains("730") && err.contains("710"), "{err}");
+        assert!(options(true, 730).check().is_ok());
+        assert!(options(false, 710).check().is_ok());
+    }
+
+    // Boots the client once for the cluster tests below; the network can't be
+    // restarted in the same process, so it is never stopped.
+    fn test_db() -> Client {
+        static BOOT: std::sync::Once = std::sync::Once::new();
+        BOOT.call_once(|| std::mem::forget(unsafe { foundationdb::boot() }));
+        let db = foundationdb::Database::default().expect("open the default cluster");
+        Client::new(db, ClientOptions::default())
+    }
+
+    // /fdbdir-test-<pid>/part is a partition holding inner, which has one key.
+    async fn make_partition(db: &Client) -> Vec<String> {
+        let root = vec![format!("fdbdir-test-{}", std::process::id())];
+        let part = [root.clone(), vec!["part".to_string()]].concat();
+        let inner = [part.clone(), vec!["inner".to_string()]].concat();
+        db.run(|trx, _| {
+            let (part, inner) = (part.clone(), inner.clone());
+            async move {
+                let dl = db.directory_layer();
+                dl.create_or_open(&trx, &part, None, Some(b"partition"))
+                    .await?;
+                let dir = dl.create_or_open(&trx, &inner, None, None).await?;
+                trx.set(&dir.pack(&("k",))?, b"v");
+                Ok(())
+            }
+        })
+        .await
+        .expect("create the partition");
+        root
+    }
+
+    async fn remove_tree(db: &Client, root: &[String]) {
+        db.run(|trx, _| async move {
+            db.directory_layer().remove_if_exists(&trx, root).await?;
+            Ok(())
+        })
+        .await
+        .expect("remove the test tree");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a FoundationDB cluster"]
+    async fn partitions_list_children_but_not_keys() {
+        let db = test_db();
+        let root = make_partition(&db).await;
+        let part = [root.clone(), vec!["part".to_string()]].concat();
+        let fmt = FormatOptions::default();
+
+        let listings = list_path(
+            Source::Db(&db),
+            root.clone(),
+            &LsOptions::default(),
+            read_at(None),
+            fmt,
+        )
+        .await
+        .unwrap();
+        let entry = &listings[0].dirs[0];
+        assert_eq!(entry.name, "part");
+        assert!(entry.partition);
+
+        let listings = list_path(
+            Source::Db(&db),
+            part.clone(),
+            &LsOptions::default(),
+            read_at(None),
+            fmt,
+        )
+        .await
+        .unwrap();
+        assert!(listings[0].partition);
+        assert!(listings[0].keys.is_none());
+        let names: Vec<_> = listings[0].dirs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["inner"]);
+
+        let mut rows: Vec<ScanRow> = Vec::new();
+        let err = scan_rows(
+            Source::Db(&db),
+            &mut rows,
+            part.clone(),
+            ScanOptions::default(),
+            read_at(None),
+            fmt,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("is a directory partition"),
+            "{err}"
+        );
+
+        let inner = [part, vec!["inner".to_string()]].concat();
+        scan_rows(
+            Source::Db(&db),
+            &mut rows,
+            inner,
+            ScanOptions::default(),
+            read_at(None),
+            fmt,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].value, b"v");
+
+        remove_tree(&db, &root).await;
+    }
 }